@@ -0,0 +1,19 @@
+prints the first eleven Fibonacci numbers
+cells: count a b tmp _ n copy divisor remainder quotient
+
++++++++++++>+>+<<
+[
+    copy a into n
+    >[->>>>+<<+<<]>>[-<<+>>]
+    divide n by ten
+    >>>>++++++++++<<
+    [->+>-[>+>>]>[+[-<+>]>+>>]<<<<<<]
+    >[-]>[-]
+    print the tens digit unless it is zero then the ones digit and a newline
+    >>[++++++++++++++++++++++++++++++++++++++++++++++++.[-]]
+    <++++++++++++++++++++++++++++++++++++++++++++++++.[-]
+    ++++++++++.[-]
+    a b becomes b (a plus b)
+    <<<<<<<[->>+<<]>[-<+>>+<]>[-<+>]
+    <<<-
+]
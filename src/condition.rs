@@ -0,0 +1,143 @@
+// conditions for `:cbreak`, one comparison between two cells or a cell and a constant
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Cell(usize),
+    Const(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellCondition {
+    pub lhs: Operand,
+    pub op: Comparison,
+    pub rhs: Operand,
+    pub text: String,
+    // the last result, so a hit is only reported when the condition becomes true
+    was_true: bool,
+}
+
+impl Operand {
+    // `cell[3]` or a plain number
+    fn parse(s: &str) -> Result<Operand, String> {
+        if let Some(index) = s.strip_prefix("cell[").and_then(|s| s.strip_suffix(']')) {
+            return index
+                .trim()
+                .parse()
+                .map(Operand::Cell)
+                .map_err(|_| format!("bad cell index '{index}'"));
+        }
+        s.parse()
+            .map(Operand::Const)
+            .map_err(|_| format!("expected cell[n] or a number, got '{s}'"))
+    }
+
+    fn value(self, mem: &[u32]) -> u64 {
+        match self {
+            Operand::Cell(cell) => mem.get(cell).copied().unwrap_or(0) as u64,
+            Operand::Const(value) => value,
+        }
+    }
+}
+
+impl CellCondition {
+    pub fn parse(text: &str) -> Result<CellCondition, String> {
+        // two char operators first so `<=` isn't read as `<`
+        const OPS: [(&str, Comparison); 6] = [
+            ("<=", Comparison::LessEq),
+            (">=", Comparison::GreaterEq),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let (lhs, op, rhs) = OPS
+            .iter()
+            .find_map(|&(token, op)| text.split_once(token).map(|(l, r)| (l, op, r)))
+            .ok_or_else(|| format!("no comparison in '{text}'"))?;
+
+        Ok(CellCondition {
+            lhs: Operand::parse(lhs.trim())?,
+            op,
+            rhs: Operand::parse(rhs.trim())?,
+            text: text.trim().to_string(),
+            was_true: false,
+        })
+    }
+
+    pub fn eval(&self, mem: &[u32]) -> bool {
+        let (lhs, rhs) = (self.lhs.value(mem), self.rhs.value(mem));
+        match self.op {
+            Comparison::Less => lhs < rhs,
+            Comparison::LessEq => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterEq => lhs >= rhs,
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+        }
+    }
+
+    // true only on the step where the condition goes from false to true
+    pub fn update(&mut self, mem: &[u32]) -> bool {
+        let now = self.eval(mem);
+        let rising = now && !self.was_true;
+        self.was_true = now;
+        rising
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cells_constants_and_two_char_operators() {
+        let condition = CellCondition::parse(" cell[3] <= 10 ").unwrap();
+        assert_eq!(condition.lhs, Operand::Cell(3));
+        assert_eq!(condition.op, Comparison::LessEq);
+        assert_eq!(condition.rhs, Operand::Const(10));
+        assert_eq!(condition.text, "cell[3] <= 10");
+
+        let condition = CellCondition::parse("cell[3]>cell[ 4 ]").unwrap();
+        assert_eq!(condition.op, Comparison::Greater);
+        assert_eq!(condition.rhs, Operand::Cell(4));
+    }
+
+    #[test]
+    fn rejects_malformed_conditions() {
+        assert!(CellCondition::parse("cell[3]").is_err());
+        assert!(CellCondition::parse("cell[x] == 1").is_err());
+        assert!(CellCondition::parse("cell[1] == y").is_err());
+    }
+
+    #[test]
+    fn eval_compares_the_current_cells() {
+        let mem = [5, 7];
+        let holds = |text| CellCondition::parse(text).unwrap().eval(&mem);
+        assert!(holds("cell[0] < cell[1]"));
+        assert!(holds("cell[1] != 5"));
+        assert!(holds("cell[0] >= 5"));
+        assert!(!holds("cell[0] == cell[1]"));
+        // cells past the tape read as 0
+        assert!(holds("cell[9] == 0"));
+    }
+
+    #[test]
+    fn update_fires_only_when_the_condition_becomes_true() {
+        let mut condition = CellCondition::parse("cell[0] > 1").unwrap();
+        let hits: Vec<bool> = [0, 2, 3, 1, 4]
+            .iter()
+            .map(|&v| condition.update(&[v]))
+            .collect();
+        assert_eq!(hits, [false, true, false, false, true]);
+    }
+}
@@ -0,0 +1,29 @@
+// programs bundled into the binary for :examples
+pub const EXAMPLES: [(&str, &[u8]); 3] = [
+    ("hello", include_bytes!("../programs/hello.bf")),
+    ("cat", include_bytes!("../programs/cat.bf")),
+    ("fib", include_bytes!("../programs/fib.bf")),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{check_brackets, BFInt, EofBehavior, StepOutcome};
+
+    #[test]
+    fn every_example_is_balanced_and_halts() {
+        for (name, prog) in EXAMPLES {
+            assert_eq!(check_brackets(prog), Ok(()), "{name}");
+
+            let mut interp = BFInt::new();
+            interp.extend_prog(prog).unwrap();
+            interp.push_input(b"hi\n");
+            interp.set_eof_behavior(EofBehavior::Zero);
+            assert_eq!(
+                interp.run_with_limit(1_000_000),
+                StepOutcome::Halted,
+                "{name}"
+            );
+        }
+    }
+}
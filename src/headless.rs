@@ -0,0 +1,113 @@
+use crate::interpreter::{BFInt, EofBehavior, StepOutcome};
+use std::io::{self, Read, Write};
+
+const STEPS_PER_FLUSH: usize = 100_000;
+
+// `-` reads the program from `stdin`, anything else is treated as a file path
+pub fn read_program(path: &str, stdin: &mut impl Read) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut prog = Vec::new();
+        stdin.read_to_end(&mut prog)?;
+        Ok(prog)
+    } else {
+        std::fs::read(path)
+    }
+}
+
+// runs a program without the tui, `,` reads from `input` and `.` writes to `output`.
+// once `input` is closed `,` follows `eof`, where Wait makes it an error.
+// the finished interpreter is handed back so its state can be inspected
+pub fn run(
+    prog: &[u8],
+    input: &mut impl Read,
+    output: &mut impl Write,
+    eof: EofBehavior,
+) -> io::Result<BFInt> {
+    let mut interp = BFInt::new();
+    interp
+        .extend_prog(prog)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let outcome = interp.run_with_limit(STEPS_PER_FLUSH);
+        output.write_all(&interp.take_output())?;
+
+        match outcome {
+            StepOutcome::Continue | StepOutcome::Output(_) => {}
+            StepOutcome::Halted => break,
+            StepOutcome::NeedsInput => match input.read(&mut buf)? {
+                0 if eof != EofBehavior::Wait => {
+                    interp.set_eof_behavior(eof);
+                }
+                0 => {
+                    output.flush()?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "program needs input but stdin is closed",
+                    ));
+                }
+                n => interp.push_input(&buf[..n]),
+            },
+            StepOutcome::PointerUnderflow => {
+                output.flush()?;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("`<` at offset {} moved left of cell 0", interp.prog_ptr),
+                ));
+            }
+            StepOutcome::PointerLimit(_)
+            | StepOutcome::FrozenWrite(_)
+            | StepOutcome::TapeLimit
+            | StepOutcome::Breakpoint(_)
+            | StepOutcome::Watchpoint(_)
+            | StepOutcome::Condition(_)
+            | StepOutcome::VisitLimit(_) => {
+                unreachable!("headless runs never set limits, frozen cells or breakpoints")
+            }
+        }
+    }
+    output.flush()?;
+    Ok(interp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cat_copies_input_until_eof() {
+        let mut output = Vec::new();
+        let interp = run(b",[.,]", &mut &b"hi"[..], &mut output, EofBehavior::Zero).unwrap();
+        assert_eq!(output, b"hi");
+        assert_eq!(interp.input_read, 2);
+    }
+
+    #[test]
+    fn cat_without_an_eof_policy_errors_at_eof() {
+        let mut output = Vec::new();
+        let err = run(b",[.,]", &mut &b"hi"[..], &mut output, EofBehavior::Wait).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(output, b"hi");
+    }
+
+    #[test]
+    fn dash_reads_the_program_from_stdin() {
+        let prog = read_program("-", &mut &b"+[-]"[..]).unwrap();
+        assert_eq!(prog, b"+[-]");
+    }
+
+    #[test]
+    fn other_paths_read_a_file_and_leave_stdin_alone() {
+        let path = std::env::temp_dir().join(format!("bfrepl-read-{}.bf", std::process::id()));
+        std::fs::write(&path, b"++.").unwrap();
+        let mut stdin = &b"unused"[..];
+        let prog = read_program(path.to_str().unwrap(), &mut stdin);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(prog.unwrap(), b"++.");
+        assert_eq!(stdin, b"unused");
+
+        let missing = read_program(path.to_str().unwrap(), &mut stdin).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+}
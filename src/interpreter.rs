@@ -1,3 +1,9 @@
+use crate::condition::CellCondition;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InterpState {
     Finished, // pc has reached end of prog
@@ -5,14 +11,320 @@ pub enum InterpState {
     Running,  // prog has pending instructions
 }
 
-#[derive(Debug)]
+pub fn is_instruction(byte: u8) -> bool {
+    matches!(byte, b'>' | b'<' | b'+' | b'-' | b'.' | b',' | b'[' | b']')
+}
+
+// largest tape that is grown to on request (loading a state, poking a cell) when no
+// tape_limit is set, 64MB of cells
+pub const MAX_TAPE_LEN: usize = 1 << 24;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// order of the per-opcode counts returned by BFInt::instruction_counts
+pub const OPCODES: [u8; 8] = *b"><+-.,[]";
+
+fn opcode_index(byte: u8) -> Option<usize> {
+    OPCODES.iter().position(|&op| op == byte)
+}
+
+// a bracket without a partner and its program offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BracketError {
+    UnmatchedClose(usize),
+    UnmatchedOpen(usize),
+}
+
+impl BracketError {
+    fn shifted(self, by: usize) -> BracketError {
+        match self {
+            BracketError::UnmatchedClose(offset) => BracketError::UnmatchedClose(offset + by),
+            BracketError::UnmatchedOpen(offset) => BracketError::UnmatchedOpen(offset + by),
+        }
+    }
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BracketError::UnmatchedClose(offset) => write!(f, "unmatched ']' at offset {offset}"),
+            BracketError::UnmatchedOpen(offset) => write!(f, "unmatched '[' at offset {offset}"),
+        }
+    }
+}
+
+// reports the first stray `]`, otherwise the innermost `[` left open
+pub fn check_brackets(prog: &[u8]) -> Result<(), BracketError> {
+    let mut open = Vec::new();
+    for (offset, &b) in prog.iter().enumerate() {
+        match b {
+            b'[' => open.push(offset),
+            b']' if open.pop().is_none() => return Err(BracketError::UnmatchedClose(offset)),
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(offset) => Err(BracketError::UnmatchedOpen(offset)),
+        None => Ok(()),
+    }
+}
+
+// one line per run of instructions with every loop body indented a level deeper,
+// whitespace is always regenerated while other comment bytes are optional
+pub fn format_source(prog: &[u8], indent: &str, keep_comments: bool) -> String {
+    let mut lines = Vec::new();
+    let mut run = String::new();
+    let mut depth = 0;
+
+    for &byte in prog {
+        if byte == b'[' || byte == b']' {
+            if !run.is_empty() {
+                lines.push(indent.repeat(depth) + &run);
+                run.clear();
+            }
+            if byte == b']' {
+                depth = depth.saturating_sub(1);
+            }
+            lines.push(indent.repeat(depth) + if byte == b'[' { "[" } else { "]" });
+            if byte == b'[' {
+                depth += 1;
+            }
+        } else if is_instruction(byte) || (keep_comments && !byte.is_ascii_whitespace()) {
+            run.push(byte as char);
+        }
+    }
+    if !run.is_empty() {
+        lines.push(indent.repeat(depth) + &run);
+    }
+
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffEdit {
+    Removed { offset: usize, op: u8 }, // only present in the old program
+    Added { offset: usize, op: u8 },   // only present in the new program
+}
+
+// instruction level diff of two programs, comments are ignored and offsets index the raw sources
+pub fn instruction_diff(old: &[u8], new: &[u8]) -> Vec<DiffEdit> {
+    let filter = |prog: &[u8]| -> Vec<(usize, u8)> {
+        prog.iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, b)| is_instruction(b))
+            .collect()
+    };
+    let old = filter(old);
+    let new = filter(new);
+
+    // lcs[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i].1 == new[j].1 {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].1 == new[j].1 {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            let (offset, op) = new[j];
+            edits.push(DiffEdit::Added { offset, op });
+            j += 1;
+        } else {
+            let (offset, op) = old[i];
+            edits.push(DiffEdit::Removed { offset, op });
+            i += 1;
+        }
+    }
+    edits
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepOutcome {
+    Continue,            // an instruction ran and more are pending
+    Halted,              // pc has reached end of prog
+    NeedsInput,          // `,` found the input queue empty
+    PointerLimit(usize), // `>` moved mem_ptr past pointer_limit
+    FrozenWrite(usize),  // an instruction tried to change a frozen cell
+    TapeLimit,           // `>` would grow the tape past tape_limit
+    PointerUnderflow,    // `<` would move left of cell 0
+    Output(u8),          // `.` ran, even if output is disabled
+    Breakpoint(usize),   // a run reached a breakpoint before executing it
+    Watchpoint(usize),   // an instruction changed a watched cell
+    Condition(usize),    // conditions[i] became true
+    VisitLimit(usize),   // one run executed the instruction at pc more than visit_limit times
+}
+
+// what happens when the program writes to a frozen cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrozenMode {
+    Ignore,
+    Pause,
+}
+
+// what `,` does when the input queue is empty
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EofBehavior {
+    Wait,      // pause with NeedsInput until more input is pushed
+    Unchanged, // leave the cell as it is
+    Zero,      // store 0
+    Max,       // store the largest value a cell can hold
+}
+
+// how many bits a cell holds, cells are stored as u32 and masked down to this
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellWidth {
+    U8,
+    U16,
+    U32,
+}
+
+impl CellWidth {
+    pub fn max(self) -> u32 {
+        match self {
+            CellWidth::U8 => u8::MAX as u32,
+            CellWidth::U16 => u16::MAX as u32,
+            CellWidth::U32 => u32::MAX,
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            CellWidth::U8 => 8,
+            CellWidth::U16 => 16,
+            CellWidth::U32 => 32,
+        }
+    }
+}
+
+// how `+` and `-` behave at the ends of the cell range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticMode {
+    Wrapping,
+    Saturating,
+}
+
+// emitted once per executed instruction when an event log is attached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Move(usize),                       // mem_ptr moved to the given cell
+    Write { cell: usize, value: u32 }, // a cell was changed by `+`, `-` or `,`
+    Output(u8),                        // `.` emitted a byte
+    LoopEnter(usize),                  // `[` at the given offset fell into its body
+    LoopSkip(usize),                   // `[` at the given offset jumped past its body
+    LoopRepeat(usize),                 // `]` at the given offset jumped back
+    LoopExit(usize),                   // `]` at the given offset fell through
+}
+
+// cells touched by one pass over a loop body, relative to the cell the loop started on
+#[derive(Debug, Default, PartialEq)]
+pub struct LoopEffects {
+    pub net_move: isize,
+    pub reads: BTreeSet<isize>,
+    pub writes: BTreeSet<isize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TraceEntry {
+    pub step: u64,
+    pub pc: usize,
+    pub opcode: u8,
+    pub mem_ptr: usize,
+    pub cell_after: u32,
+}
+
+// execution state that can be put back later. the program itself is not included,
+// only enough of it to tell whether it has been replaced since
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    prog_len: usize,
+    prog_hash: u64,
+    prog_ptr: usize,
+    mem: Vec<u32>,
+    mem_ptr: usize,
+    state: InterpState,
+    input: VecDeque<u8>,
+    step_count: u64,
+    pc_counts: Vec<u64>,
+    op_counts: [u64; 8],
+    high_water: usize,
+}
+
+// what one executed instruction changed, so step_back can put it back
+#[derive(Debug, Clone, Copy)]
+struct UndoEntry {
+    pc: usize, // where the instruction was, even if it jumped somewhere else
+    jumped: bool,
+    mem_ptr: usize,
+    cell: u32,             // value of mem[mem_ptr] before the instruction
+    consumed: Option<u8>,  // input byte taken by `,`
+    buffered_output: bool, // `.` pushed a byte onto `output`
+}
+
+const UNDO_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone)]
 pub struct BFInt {
     pub prog: Vec<u8>,
     pub prog_ptr: usize,
-    pub mem: Vec<u8>,
+    pub mem: Vec<u32>,
     pub mem_ptr: usize,
+    // furthest right mem_ptr has been moved by `>`
+    high_water: usize,
     pub loop_map: Vec<(usize, usize)>,
+    // partner of the bracket at each offset, None for everything else
+    jump: Vec<Option<usize>>,
     pub state: InterpState,
+    pub input: VecDeque<u8>,
+    // bytes taken off the input queue by `,`
+    pub input_read: u64,
+    output: Vec<u8>,
+    pub events: Option<Vec<Event>>,
+    pub arithmetic: ArithmeticMode,
+    pub cell_width: CellWidth,
+    pub eof_behavior: EofBehavior,
+    pub pointer_limit: Option<usize>,
+    pub step_count: u64,
+    pub trace: Option<VecDeque<TraceEntry>>,
+    pub trace_capacity: usize,
+    pub pc_counts: Vec<u64>,
+    // executions of each opcode, in OPCODES order
+    op_counts: [u64; 8],
+    pub output_count: u64,
+    output_enabled: bool,
+    pub frozen: HashSet<usize>,
+    pub frozen_mode: FrozenMode,
+    pub tape_limit: Option<usize>,
+    // replacement text shown for particular output bytes
+    pub output_table: HashMap<u8, String>,
+    pub breakpoints: HashSet<usize>,
+    pub watchpoints: HashSet<usize>,
+    pub conditions: Vec<CellCondition>,
+    undo_log: VecDeque<UndoEntry>,
+    // set for a single run to go straight through breakpoints and watchpoints
+    pub ignore_breakpoints: bool,
+    // (min, max) seen per cell since tracking was turned on
+    pub cell_ranges: Option<Vec<(u32, u32)>>,
+    // catches tight infinite loops during run_with_limit
+    pub visit_limit: Option<u64>,
+    // pc_counts when the current run began, visits are counted per run
+    run_counts: Vec<u64>,
+    // snippets held back by extend_prog_partial until their loops close
+    pending: Vec<u8>,
 }
 
 impl BFInt {
@@ -22,87 +334,1472 @@ impl BFInt {
             prog_ptr: 0,
             mem: vec![0; 1000],
             mem_ptr: 0,
+            high_water: 0,
             loop_map: Vec::new(),
+            jump: Vec::new(),
             state: InterpState::Finished,
+            input: VecDeque::new(),
+            input_read: 0,
+            output: Vec::new(),
+            events: None,
+            arithmetic: ArithmeticMode::Wrapping,
+            cell_width: CellWidth::U8,
+            eof_behavior: EofBehavior::Wait,
+            pointer_limit: None,
+            step_count: 0,
+            trace: None,
+            trace_capacity: 0,
+            pc_counts: Vec::new(),
+            op_counts: [0; 8],
+            output_count: 0,
+            output_enabled: true,
+            frozen: HashSet::new(),
+            frozen_mode: FrozenMode::Ignore,
+            tape_limit: None,
+            output_table: HashMap::new(),
+            breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            conditions: Vec::new(),
+            undo_log: VecDeque::new(),
+            cell_ranges: None,
+            visit_limit: None,
+            run_counts: Vec::new(),
+            pending: Vec::new(),
+            ignore_breakpoints: false,
+        }
+    }
+
+    pub fn with_cell_width(width: CellWidth) -> BFInt {
+        BFInt {
+            cell_width: width,
+            ..BFInt::new()
+        }
+    }
+
+    // narrowing the width truncates every cell to fit
+    pub fn set_cell_width(&mut self, width: CellWidth) {
+        self.cell_width = width;
+        let max = width.max();
+        for cell in &mut self.mem {
+            *cell &= max;
         }
     }
 
-    pub fn extend_prog(&mut self, new_prog: &[u8]) {
+    // the program is left alone if `new_prog` has unbalanced brackets
+    pub fn extend_prog(&mut self, new_prog: &[u8]) -> Result<(), BracketError> {
+        let start = self.prog.len();
+        check_brackets(new_prog).map_err(|e| e.shifted(start))?;
         self.prog.extend_from_slice(new_prog);
-        self.extend_loop_map();
+        self.pc_counts.resize(self.prog.len(), 0);
+        self.extend_loop_map(start);
 
         if self.prog_ptr < self.prog.len() {
             self.state = InterpState::Running;
         }
+        Ok(())
+    }
+
+    // like extend_prog, but a snippet that leaves a loop open is held back and joined
+    // with the following ones until it closes. false while something is held back
+    pub fn extend_prog_partial(&mut self, snippet: &[u8]) -> Result<bool, BracketError> {
+        let mut combined = self.pending.clone();
+        combined.extend_from_slice(snippet);
+        match check_brackets(&combined) {
+            Err(BracketError::UnmatchedOpen(_)) => {
+                self.pending = combined;
+                Ok(false)
+            }
+            Err(e) => Err(e.shifted(self.prog.len())),
+            Ok(()) => {
+                self.pending.clear();
+                self.extend_prog(&combined)?;
+                Ok(true)
+            }
+        }
     }
 
-    fn extend_loop_map(&mut self) {
-        // would be better to keep the loop map as a sorted array based on the source index
-        let mut pc = self.prog_ptr;
+    // partner of the bracket at `offset`, None if there is no bracket there
+    pub fn matching_bracket(&self, offset: usize) -> Option<usize> {
+        self.jump.get(offset).copied().flatten()
+    }
+
+    // instructions that can still run starting from `pc`, taking both sides of every
+    // bracket. earlier loop bodies become reachable again through `]` back jumps
+    pub fn reachable_from(&self, pc: usize) -> usize {
+        let mut seen = vec![false; self.prog.len()];
+        let mut pending = vec![pc];
+        while let Some(at) = pending.pop() {
+            if at >= self.prog.len() || seen[at] {
+                continue;
+            }
+            seen[at] = true;
+            pending.push(at + 1);
+            if let Some(partner) = self.matching_bracket(at) {
+                pending.push(partner + 1);
+            }
+        }
+        seen.iter()
+            .zip(&self.prog)
+            .filter(|&(&seen, &b)| seen && is_instruction(b))
+            .count()
+    }
+
+    // `.` instructions outside any loop, and whether that is the exact output size.
+    // loops may run any number of times, so output inside one makes the count a lower bound
+    pub fn static_output_count(&self) -> (usize, bool) {
+        let mut depth = 0usize;
+        let mut count = 0;
+        let mut exact = true;
+        for &op in &self.prog {
+            match op {
+                b'[' => depth += 1,
+                b']' => depth = depth.saturating_sub(1),
+                b'.' if depth == 0 => count += 1,
+                b'.' => exact = false,
+                _ => {}
+            }
+        }
+        (count, exact)
+    }
+
+    // nested loops are treated as running once, so their effects are only a guide
+    pub fn loop_effects(&self, open: usize) -> Option<LoopEffects> {
+        if self.prog.get(open) != Some(&b'[') {
+            return None;
+        }
+        let close = self.matching_bracket(open)?;
+
+        let mut effects = LoopEffects::default();
+        // both brackets test the current cell
+        effects.reads.insert(0);
+        for &op in &self.prog[open + 1..close] {
+            let pos = effects.net_move;
+            match op {
+                b'>' => effects.net_move += 1,
+                b'<' => effects.net_move -= 1,
+                b'+' | b'-' => {
+                    effects.reads.insert(pos);
+                    effects.writes.insert(pos);
+                }
+                b',' => {
+                    effects.writes.insert(pos);
+                }
+                b'.' | b'[' | b']' => {
+                    effects.reads.insert(pos);
+                }
+                _ => {}
+            }
+        }
+        effects.reads.insert(effects.net_move);
+        Some(effects)
+    }
+
+    // lowest and highest cell offsets the program can reach relative to its starting cell,
+    // None when a loop moves the pointer by a data dependent amount
+    pub fn static_extent(&self) -> Option<(isize, isize)> {
+        self.extent_of(0, self.prog.len())
+            .map(|(_, min, max)| (min, max))
+    }
+
+    // net movement and reach of prog[start..end] relative to the pointer at `start`
+    fn extent_of(&self, start: usize, end: usize) -> Option<(isize, isize, isize)> {
+        let (mut pos, mut min, mut max) = (0isize, 0isize, 0isize);
+        let mut pc = start;
+        while pc < end {
+            match self.prog[pc] {
+                b'>' => pos += 1,
+                b'<' => pos -= 1,
+                b'[' => {
+                    let close = self.matching_bracket(pc)?;
+                    let (net, lo, hi) = self.extent_of(pc + 1, close)?;
+                    // an unbalanced body drifts by an unknown number of iterations
+                    if net != 0 {
+                        return None;
+                    }
+                    min = min.min(pos + lo);
+                    max = max.max(pos + hi);
+                    pc = close;
+                }
+                b']' => return None,
+                _ => {}
+            }
+            min = min.min(pos);
+            max = max.max(pos);
+            pc += 1;
+        }
+        Some((pos, min, max))
+    }
+
+    pub fn format_indented(&self, indent: &str) -> String {
+        format_source(&self.prog, indent, false)
+    }
+
+    // rewinds to the start of the program with a zeroed tape, keeping the program
+    // and its loop map
+    pub fn reset(&mut self) {
+        self.prog_ptr = 0;
+        self.mem_ptr = 0;
+        self.high_water = 0;
+        self.mem.fill(0);
+        self.input.clear();
+        self.input_read = 0;
+        self.output.clear();
+        self.output_count = 0;
+        self.step_count = 0;
+        self.pc_counts.fill(0);
+        self.op_counts = [0; 8];
+        self.undo_log.clear();
+        self.pending.clear();
+        if self.cell_ranges.is_some() {
+            self.enable_ranges();
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+        self.state = if self.prog.is_empty() {
+            InterpState::Finished
+        } else {
+            InterpState::Running
+        };
+    }
+
+    // swaps in a new program. callers must reset() afterwards unless the pc is still at
+    // the start or they restore the run state themselves
+    pub fn replace_prog(&mut self, new_prog: &[u8]) -> Result<(), BracketError> {
+        check_brackets(new_prog)?;
+        self.pending.clear();
+        self.prog.clear();
+        self.loop_map.clear();
+        self.jump.clear();
+        self.pc_counts.clear();
+        self.undo_log.clear();
+        self.prog_ptr = 0;
+        self.state = InterpState::Finished;
+        self.extend_prog(new_prog)
+    }
+
+    // replace_prog for the same instructions laid out differently, breakpoints stay on
+    // the instruction they were set on
+    pub fn relayout_prog(&mut self, new_prog: &[u8]) -> Result<(), BracketError> {
+        let before = |pc: usize| {
+            self.prog[..pc.min(self.prog.len())]
+                .iter()
+                .filter(|&&b| is_instruction(b))
+                .count()
+        };
+        let indices: Vec<usize> = self.breakpoints.iter().map(|&pc| before(pc)).collect();
+        self.replace_prog(new_prog)?;
+
+        let offsets: Vec<usize> = (0..self.prog.len())
+            .filter(|&pc| is_instruction(self.prog[pc]))
+            .collect();
+        self.breakpoints = indices
+            .into_iter()
+            .map(|i| offsets.get(i).copied().unwrap_or(self.prog.len()))
+            .collect();
+        Ok(())
+    }
+
+    // inserts a note at `offset`, dropping instruction chars so behaviour is unchanged
+    pub fn annotate(&mut self, offset: usize, note: &str) -> usize {
+        let note: Vec<u8> = note.bytes().filter(|&b| !is_instruction(b)).collect();
+        let offset = offset.min(self.prog.len());
+        self.prog.splice(offset..offset, note.iter().copied());
+        self.pc_counts.splice(offset..offset, vec![0; note.len()]);
+
+        let shift = |pc: usize| if pc >= offset { pc + note.len() } else { pc };
+        self.prog_ptr = shift(self.prog_ptr);
+        self.breakpoints = self.breakpoints.iter().map(|&pc| shift(pc)).collect();
+        self.loop_map.clear();
+        self.jump.clear();
+        self.extend_loop_map(0);
+        self.undo_log.clear();
+        note.len()
+    }
+
+    // number of loops whose brackets enclose the pc, the brackets themselves are outside
+    pub fn current_depth(&self) -> usize {
+        self.loop_map
+            .iter()
+            .filter(|&&(open, close)| open < self.prog_ptr && self.prog_ptr < close)
+            .count()
+    }
+
+    // fnv-1a over the instructions only, so comments and layout don't change it
+    pub fn instruction_hash(&self) -> u64 {
+        let instructions: Vec<u8> = self
+            .prog
+            .iter()
+            .copied()
+            .filter(|&b| is_instruction(b))
+            .collect();
+        fnv1a(&instructions)
+    }
+
+    // highest cell the pointer has reached, including where it was put by hand
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water.max(self.mem_ptr)
+    }
+
+    // how many times each opcode has run, in OPCODES order
+    pub fn instruction_counts(&self) -> [u64; 8] {
+        self.op_counts
+    }
+
+    // number of bytes in the program that are not comments
+    pub fn instruction_count(&self) -> usize {
+        self.prog.iter().filter(|&&b| is_instruction(b)).count()
+    }
+
+    // a `,` already waiting for input follows the new policy on the next step
+    pub fn set_eof_behavior(&mut self, eof: EofBehavior) {
+        self.eof_behavior = eof;
+        if eof != EofBehavior::Wait && self.state == InterpState::Waiting {
+            self.state = InterpState::Running;
+        }
+    }
+
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+
+        if self.state == InterpState::Waiting {
+            self.state = InterpState::Running;
+        }
+    }
+
+    fn extend_loop_map(&mut self, start: usize) {
+        self.jump.resize(self.prog.len(), None);
+        let mut pc = start;
         let mut start_stack: Vec<usize> = Vec::new();
         while pc < self.prog.len() {
             match self.prog[pc] {
-                b'[' => start_stack.push(pc + self.prog_ptr),
-                b']' => self
-                    .loop_map
-                    .push((start_stack.pop().unwrap(), self.prog_ptr + pc)),
+                b'[' => start_stack.push(pc),
+                b']' => {
+                    // extend_prog has already checked the brackets balance
+                    let open = start_stack.pop().unwrap();
+                    self.loop_map.push((open, pc));
+                    self.jump[open] = Some(pc);
+                    self.jump[pc] = Some(open);
+                }
                 _ => {}
             }
             pc += 1;
         }
     }
 
-    fn ensure_allocated(&mut self, index: usize) {
+    // copies `image` over the leading cells, growing the tape if it is too short
+    pub fn load_tape(&mut self, image: &[u8]) {
+        if image.len() > self.mem.len() {
+            self.mem.resize(image.len(), 0);
+        }
+        for (cell, &byte) in self.mem.iter_mut().zip(image) {
+            *cell = byte as u32;
+        }
+        self.mem_ptr = 0;
+        self.undo_log.clear();
+    }
+
+    // writes a cell directly, false if it lies past tape_limit
+    pub fn poke(&mut self, cell: usize, value: u32) -> bool {
+        if !self.ensure_allocated(cell) {
+            return false;
+        }
+        self.mem[cell] = value & self.cell_width.max();
+        true
+    }
+
+    // returns false instead of growing the tape past tape_limit
+    fn ensure_allocated(&mut self, index: usize) -> bool {
         if index >= self.mem.len() {
+            if self.tape_limit.is_some_and(|max| index >= max) {
+                return false;
+            }
             // probablly very inneficient
-            self.mem.resize(index, 0);
+            self.mem.resize(index + 1, 0);
+        }
+        true
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            prog_len: self.prog.len(),
+            prog_hash: fnv1a(&self.prog),
+            prog_ptr: self.prog_ptr,
+            mem: self.mem.clone(),
+            mem_ptr: self.mem_ptr,
+            state: self.state,
+            input: self.input.clone(),
+            step_count: self.step_count,
+            pc_counts: self.pc_counts.clone(),
+            op_counts: self.op_counts,
+            high_water: self.high_water,
+        }
+    }
+
+    // false, leaving everything alone, unless the program is the one the snapshot was
+    // taken on, possibly with more appended
+    pub fn restore(&mut self, snapshot: &Snapshot) -> bool {
+        let same_prog = self
+            .prog
+            .get(..snapshot.prog_len)
+            .is_some_and(|prog| fnv1a(prog) == snapshot.prog_hash);
+        if !same_prog {
+            return false;
         }
+
+        self.prog_ptr = snapshot.prog_ptr;
+        self.mem.clone_from(&snapshot.mem);
+        self.mem_ptr = snapshot.mem_ptr;
+        self.state = snapshot.state;
+        self.input.clone_from(&snapshot.input);
+        self.step_count = snapshot.step_count;
+        self.undo_log.clear();
+        // counts for anything appended since start over, like in extend_prog
+        self.pc_counts.clone_from(&snapshot.pc_counts);
+        self.pc_counts.resize(self.prog.len(), 0);
+        self.run_counts.clone_from(&self.pc_counts);
+        self.op_counts = snapshot.op_counts;
+        self.high_water = snapshot.high_water;
+
+        // the program may have grown since the snapshot was taken
+        if self.state == InterpState::Finished && self.prog_ptr < self.prog.len() {
+            self.state = InterpState::Running;
+        }
+        true
+    }
+
+    // (cell, expected, actual) for every leading cell that differs from `expected`
+    pub fn tape_mismatches(&self, expected: &[u32]) -> Vec<(usize, u32, u32)> {
+        expected
+            .iter()
+            .enumerate()
+            .filter_map(|(cell, &want)| {
+                let have = self.mem.get(cell).copied().unwrap_or(0);
+                (have != want).then_some((cell, want, have))
+            })
+            .collect()
+    }
+
+    // starts recording events, dropping any that were not yet taken
+    pub fn enable_events(&mut self) {
+        self.events = Some(Vec::new());
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // bytes written by `.` that haven't been taken yet
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    // drops untaken output past the first `len` bytes
+    pub fn truncate_output(&mut self, len: usize) {
+        self.output.truncate(len);
+    }
+
+    // when disabled `.` is still counted but writes nowhere
+    pub fn set_output_enabled(&mut self, enabled: bool) {
+        self.output_enabled = enabled;
+    }
+
+    pub fn output_enabled(&self) -> bool {
+        self.output_enabled
+    }
+
+    // the `n` most executed program offsets, busiest first
+    pub fn hotspots(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut hot: Vec<(usize, u64)> = self
+            .pc_counts
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(n);
+        hot
+    }
+
+    // keeps the last `capacity` executed instructions
+    pub fn enable_trace(&mut self, capacity: usize) {
+        self.trace = Some(VecDeque::new());
+        self.trace_capacity = capacity;
     }
 
-    pub fn step(&mut self) {
-        if self.state != InterpState::Running {
+    fn record_trace(&mut self, pc: usize, opcode: u8) {
+        let Some(trace) = self.trace.as_mut() else {
             return;
+        };
+        if trace.len() >= self.trace_capacity {
+            trace.pop_front();
         }
+        trace.push_back(TraceEntry {
+            step: self.step_count,
+            pc,
+            opcode,
+            mem_ptr: self.mem_ptr,
+            cell_after: self.mem[self.mem_ptr],
+        });
+    }
+
+    pub fn trace_csv(&self) -> Option<String> {
+        let trace = self.trace.as_ref().filter(|t| !t.is_empty())?;
+        let mut csv = String::from("step,pc,opcode,mem_ptr,cell_after\n");
+        for entry in trace {
+            // comment bytes are traced too, so a quote has to be doubled
+            let opcode = (entry.opcode as char).to_string().replace('"', "\"\"");
+            csv.push_str(&format!(
+                "{},{},\"{opcode}\",{},{}\n",
+                entry.step, entry.pc, entry.mem_ptr, entry.cell_after
+            ));
+        }
+        Some(csv)
+    }
 
-        match self.prog[self.prog_ptr] {
-            b'>' => self.mem_ptr += 1,
-            b'<' => self.mem_ptr -= 1,
-            b'+' => self.mem[self.mem_ptr] = self.mem[self.mem_ptr].wrapping_add(1),
-            b'-' => self.mem[self.mem_ptr] = self.mem[self.mem_ptr].wrapping_sub(1),
-            b'.' => print!("{}", self.mem[self.mem_ptr] as char),
-            b',' => todo!(),
-            b'[' => {
-                if self.mem[self.mem_ptr] == 0 {
-                    self.prog_ptr = self
-                        .loop_map
-                        .iter()
-                        .find(|&(s, _)| *s == self.prog_ptr)
-                        .unwrap()
-                        .1;
+    // one json object per traced step, `changed` lists the [cell, value] pairs it wrote
+    pub fn trace_frames(&self) -> Vec<String> {
+        let Some(trace) = self.trace.as_ref() else {
+            return Vec::new();
+        };
+        trace
+            .iter()
+            .map(|entry| {
+                let changed = match entry.opcode {
+                    b'+' | b'-' | b',' => format!("[[{},{}]]", entry.mem_ptr, entry.cell_after),
+                    _ => "[]".to_string(),
+                };
+                format!(
+                    "{{\"step\":{},\"pc\":{},\"mem_ptr\":{},\"changed\":{changed}}}",
+                    entry.step, entry.pc, entry.mem_ptr
+                )
+            })
+            .collect()
+    }
+
+    // renders output for display, swapping in any entries from the output table
+    pub fn translate_output(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        let mut pending = Vec::new();
+        for &byte in bytes {
+            match self.output_table.get(&byte) {
+                Some(replacement) => {
+                    text.push_str(&String::from_utf8_lossy(&pending));
+                    pending.clear();
+                    text.push_str(replacement);
                 }
+                None => pending.push(byte),
             }
-            b']' => {
-                if self.mem[self.mem_ptr] != 0 {
-                    self.prog_ptr = self
-                        .loop_map
-                        .iter()
-                        .find(|&(_, d)| *d == self.prog_ptr)
-                        .unwrap()
-                        .0;
+        }
+        text.push_str(&String::from_utf8_lossy(&pending));
+        text
+    }
+
+    fn emit(&mut self, event: Event) {
+        if let Some(events) = self.events.as_mut() {
+            events.push(event);
+        }
+    }
+
+    fn increment(&self, value: u32) -> u32 {
+        let max = self.cell_width.max();
+        match self.arithmetic {
+            ArithmeticMode::Wrapping => value.wrapping_add(1) & max,
+            ArithmeticMode::Saturating => value.saturating_add(1).min(max),
+        }
+    }
+
+    fn decrement(&self, value: u32) -> u32 {
+        let max = self.cell_width.max();
+        match self.arithmetic {
+            ArithmeticMode::Wrapping => value.wrapping_sub(1) & max,
+            ArithmeticMode::Saturating => value.saturating_sub(1),
+        }
+    }
+
+    // changes the current cell by one without executing an instruction
+    // frozen cells are only protected from the program, not from the user
+    pub fn adjust_cell(&mut self, increase: bool) {
+        let value = self.mem[self.mem_ptr];
+        if increase {
+            self.store_cell(self.increment(value));
+        } else {
+            self.store_cell(self.decrement(value));
+        }
+    }
+
+    // returns the outcome to pause with if the write hit a frozen cell
+    fn write_cell(&mut self, value: u32) -> Option<StepOutcome> {
+        if !self.frozen.contains(&self.mem_ptr) {
+            self.store_cell(value);
+            if !self.ignore_breakpoints && self.watchpoints.contains(&self.mem_ptr) {
+                return Some(StepOutcome::Watchpoint(self.mem_ptr));
+            }
+            return None;
+        }
+
+        match self.frozen_mode {
+            FrozenMode::Ignore => None,
+            FrozenMode::Pause => Some(StepOutcome::FrozenWrite(self.mem_ptr)),
+        }
+    }
+
+    // cells start out ranging over the value they held when tracking began
+    pub fn enable_ranges(&mut self) {
+        self.cell_ranges = Some(self.mem.iter().map(|&v| (v, v)).collect());
+    }
+
+    fn store_cell(&mut self, value: u32) {
+        if let Some(ranges) = self.cell_ranges.as_mut() {
+            // the tape may have grown since tracking began
+            while ranges.len() <= self.mem_ptr {
+                let v = self.mem[ranges.len()];
+                ranges.push((v, v));
+            }
+            let (min, max) = &mut ranges[self.mem_ptr];
+            *min = (*min).min(value);
+            *max = (*max).max(value);
+        }
+        self.mem[self.mem_ptr] = value;
+        self.emit(Event::Write {
+            cell: self.mem_ptr,
+            value,
+        });
+    }
+
+    pub fn step(&mut self) -> StepOutcome {
+        match self.state {
+            InterpState::Finished => return StepOutcome::Halted,
+            InterpState::Waiting => return StepOutcome::NeedsInput,
+            InterpState::Running => {}
+        }
+        let mut outcome = None;
+        let pc = self.prog_ptr;
+        let opcode = self.prog[pc];
+        let mut undo = UndoEntry {
+            pc,
+            mem_ptr: self.mem_ptr,
+            cell: self.mem[self.mem_ptr],
+            jumped: false,
+            consumed: None,
+            buffered_output: false,
+        };
+
+        match opcode {
+            b'>' => {
+                if !self.ensure_allocated(self.mem_ptr + 1) {
+                    // leave the pc on the `>` so raising the limit lets it continue
+                    return StepOutcome::TapeLimit;
+                }
+                self.mem_ptr += 1;
+                self.high_water = self.high_water.max(self.mem_ptr);
+                self.emit(Event::Move(self.mem_ptr));
+                if self.pointer_limit.is_some_and(|max| self.mem_ptr > max) {
+                    outcome = Some(StepOutcome::PointerLimit(self.mem_ptr));
+                }
+            }
+            b'<' => {
+                if self.mem_ptr == 0 {
+                    // like TapeLimit the pc stays on the `<`
+                    return StepOutcome::PointerUnderflow;
+                }
+                self.mem_ptr -= 1;
+                self.emit(Event::Move(self.mem_ptr));
+            }
+            b'+' => outcome = self.write_cell(self.increment(self.mem[self.mem_ptr])),
+            b'-' => outcome = self.write_cell(self.decrement(self.mem[self.mem_ptr])),
+            b'.' => {
+                // wider cells are written out as their low byte
+                let byte = self.mem[self.mem_ptr] as u8;
+                self.output_count += 1;
+                if self.output_enabled {
+                    undo.buffered_output = true;
+                    self.output.push(byte);
+                    self.emit(Event::Output(byte));
+                }
+                outcome = Some(StepOutcome::Output(byte));
+            }
+            b',' => match (self.input.pop_front(), self.eof_behavior) {
+                (Some(byte), _) => {
+                    undo.consumed = Some(byte);
+                    self.input_read += 1;
+                    outcome = self.write_cell(byte as u32);
+                }
+                (None, EofBehavior::Wait) => {
+                    // leave the pc on the `,` so it is retried once input arrives
+                    self.state = InterpState::Waiting;
+                    return StepOutcome::NeedsInput;
                 }
+                (None, EofBehavior::Unchanged) => {}
+                (None, EofBehavior::Zero) => outcome = self.write_cell(0),
+                (None, EofBehavior::Max) => outcome = self.write_cell(self.cell_width.max()),
+            },
+            b'[' if self.mem[self.mem_ptr] == 0 => {
+                undo.jumped = true;
+                self.emit(Event::LoopSkip(self.prog_ptr));
+                self.prog_ptr = self.jump[self.prog_ptr].unwrap();
             }
+            b'[' => self.emit(Event::LoopEnter(self.prog_ptr)),
+            b']' if self.mem[self.mem_ptr] != 0 => {
+                undo.jumped = true;
+                self.emit(Event::LoopRepeat(self.prog_ptr));
+                self.prog_ptr = self.jump[self.prog_ptr].unwrap();
+            }
+            b']' => self.emit(Event::LoopExit(self.prog_ptr)),
             _ => {} // ignore all non-relevant bytes
         }
+        self.step_count += 1;
+        self.pc_counts[pc] += 1;
+        if let Some(op) = opcode_index(opcode) {
+            self.op_counts[op] += 1;
+        }
+        self.record_trace(pc, opcode);
+        if self.undo_log.len() == UNDO_CAPACITY {
+            self.undo_log.pop_front();
+        }
+        self.undo_log.push_back(undo);
         self.prog_ptr += 1;
 
         if self.prog_ptr >= self.prog.len() {
             self.state = InterpState::Finished;
         }
+        let hit = self.check_conditions();
+        if outcome.is_none() {
+            outcome = hit.map(StepOutcome::Condition);
+        }
+
+        match outcome {
+            Some(outcome) => outcome,
+            None if self.state == InterpState::Finished => StepOutcome::Halted,
+            None => StepOutcome::Continue,
+        }
     }
 
+    // reverses the last executed instruction. output already moved out of `output`
+    // stays where it went, and a grown tape is not shrunk
+    pub fn step_back(&mut self) -> bool {
+        let Some(undo) = self.undo_log.pop_back() else {
+            return false;
+        };
+
+        if self.prog[undo.pc] == b'.' {
+            self.output_count -= 1;
+            if undo.buffered_output {
+                self.output.pop();
+            }
+        }
+        if let Some(byte) = undo.consumed {
+            self.input.push_front(byte);
+            self.input_read -= 1;
+        }
+        self.prog_ptr = undo.pc;
+        self.mem_ptr = undo.mem_ptr;
+        self.mem[undo.mem_ptr] = undo.cell;
+        // a replay records the step again, so it mustn't be in the trace twice
+        if let Some(trace) = self.trace.as_mut() {
+            if trace
+                .back()
+                .is_some_and(|entry| entry.step == self.step_count)
+            {
+                trace.pop_back();
+            }
+        }
+        self.step_count -= 1;
+        self.pc_counts[undo.pc] -= 1;
+        if let Some(op) = opcode_index(self.prog[undo.pc]) {
+            self.op_counts[op] -= 1;
+        }
+        self.state = InterpState::Running;
+        for condition in &mut self.conditions {
+            condition.update(&self.mem);
+        }
+        true
+    }
+
+    // steps back to just after the innermost enclosing `[` was last evaluated, returning
+    // how many steps were undone. None, with nothing undone, if the log doesn't reach it
+    pub fn rewind_to_iteration_start(&mut self) -> Option<usize> {
+        let (open, _) = self
+            .loop_map
+            .iter()
+            .filter(|&&(open, close)| open < self.prog_ptr && self.prog_ptr < close)
+            .max_by_key(|&&(open, _)| open)?;
+        let start = open + 1;
+        if self.prog_ptr == start {
+            return Some(0);
+        }
+
+        // the iteration starts at the last time something ran at `start`
+        let steps = self.undo_log.iter().rev().position(|u| u.pc == start)? + 1;
+        for _ in 0..steps {
+            self.step_back();
+        }
+        Some(steps)
+    }
+
+    // how many of the last `n` undoable steps were taken loop jumps
+    pub fn recent_jumps(&self, n: usize) -> usize {
+        self.undo_log
+            .iter()
+            .rev()
+            .take(n)
+            .filter(|u| u.jumped)
+            .count()
+    }
+
+    // every condition is updated so later edges are still seen, the first new hit is returned
+    fn check_conditions(&mut self) -> Option<usize> {
+        let mut hit = None;
+        for (i, condition) in self.conditions.iter_mut().enumerate() {
+            if condition.update(&self.mem) && hit.is_none() && !self.ignore_breakpoints {
+                hit = Some(i);
+            }
+        }
+        hit
+    }
+
+    // the instruction under the pc has a breakpoint that is currently honoured
+    pub fn at_breakpoint(&self) -> bool {
+        !self.ignore_breakpoints && self.breakpoints.contains(&self.prog_ptr)
+    }
+
+    // steps until the program stops on its own, returns Continue if `max_steps` ran out first
+    // a breakpoint on the first instruction is skipped so a paused run can resume
+    pub fn run_with_limit(&mut self, max_steps: usize) -> StepOutcome {
+        self.begin_run();
+        self.resume_run(max_steps)
+    }
+
+    // starts counting visits for visit_limit from zero
+    pub fn begin_run(&mut self) {
+        self.run_counts.clone_from(&self.pc_counts);
+    }
+
+    // run_with_limit without starting a new run, so a run can be split across calls
+    pub fn resume_run(&mut self, max_steps: usize) -> StepOutcome {
+        self.run_until(usize::MAX, max_steps)
+    }
+
+    // Continue once the pc reaches `end` or `max_steps` have run
+    fn run_until(&mut self, end: usize, max_steps: usize) -> StepOutcome {
+        for i in 0..max_steps {
+            if self.prog_ptr >= end {
+                return StepOutcome::Continue;
+            }
+            if i > 0 && self.at_breakpoint() {
+                return StepOutcome::Breakpoint(self.prog_ptr);
+            }
+            let pc = self.prog_ptr;
+            match self.step() {
+                StepOutcome::Continue | StepOutcome::Output(_) => {}
+                outcome => return outcome,
+            }
+            if let Some(limit) = self.visit_limit {
+                let start = self.run_counts.get(pc).copied().unwrap_or(0);
+                if self.pc_counts[pc].saturating_sub(start) > limit {
+                    return StepOutcome::VisitLimit(pc);
+                }
+            }
+        }
+        StepOutcome::Continue
+    }
+
+    // steps up to `n` times, returning how many instructions ran, the output they
+    // produced and the outcome of the last step
+    pub fn run_steps(&mut self, n: u64) -> (u64, Vec<u8>, StepOutcome) {
+        let start_steps = self.step_count;
+        let start_output = self.output.len();
+        let mut outcome = StepOutcome::Continue;
+        for i in 0..n {
+            if i > 0 && self.at_breakpoint() {
+                outcome = StepOutcome::Breakpoint(self.prog_ptr);
+                break;
+            }
+            outcome = self.step();
+            if !matches!(outcome, StepOutcome::Continue | StepOutcome::Output(_)) {
+                break;
+            }
+        }
+        let output = self.output[start_output.min(self.output.len())..].to_vec();
+        (self.step_count - start_steps, output, outcome)
+    }
+
+    // run_with_limit that also stops once the pc reaches `end`, returning Continue for
+    // that and for running out of steps
+    pub fn run_to(&mut self, end: usize, max_steps: usize) -> StepOutcome {
+        self.begin_run();
+        self.run_until(end, max_steps)
+    }
+
+    // no step budget, so only for programs known to halt. stops instead of
+    // spinning when the pc can't move (waiting for input, tape limit, ...)
+    #[allow(dead_code)]
     pub fn run(&mut self) {
-        while self.prog_ptr < self.prog.len() {
-            self.step();
+        while let StepOutcome::Continue | StepOutcome::Output(_) = self.step() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_puts_back_the_snapshot() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+>++").unwrap();
+        interp.step();
+        let snapshot = interp.snapshot();
+        interp.run_with_limit(10);
+
+        assert!(interp.restore(&snapshot));
+        assert_eq!((interp.prog_ptr, interp.mem_ptr), (1, 0));
+        assert_eq!(&interp.mem[..2], &[1, 0]);
+        assert_eq!(interp.state, InterpState::Running);
+        assert_eq!(interp.pc_counts, [1, 0, 0, 0]);
+        assert_eq!(interp.instruction_counts().iter().sum::<u64>(), 1);
+        assert_eq!(interp.high_water_mark(), 0);
+    }
+
+    #[test]
+    fn restore_allows_an_extended_program() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+").unwrap();
+        interp.step();
+        let snapshot = interp.snapshot();
+        interp.extend_prog(b"+").unwrap();
+
+        assert!(interp.restore(&snapshot));
+        assert_eq!(interp.state, InterpState::Running);
+    }
+
+    #[test]
+    fn restore_refuses_a_replaced_program() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++++++++").unwrap();
+        interp.run_with_limit(100);
+        let snapshot = interp.snapshot();
+        interp.replace_prog(b"+").unwrap();
+
+        assert!(!interp.restore(&snapshot));
+        assert_eq!(interp.prog_ptr, 0);
+        interp.step();
+    }
+
+    #[test]
+    fn input_queue_length_tracks_pushes_and_reads() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b",,").unwrap();
+        interp.push_input(b"abc");
+        assert_eq!(interp.input.len(), 3);
+        interp.step();
+        interp.step();
+        assert_eq!(interp.input.len(), 1);
+    }
+
+    #[test]
+    fn comma_reads_queued_input() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b",").unwrap();
+        interp.push_input(b"A");
+        interp.step();
+        assert_eq!(interp.mem[0], 65);
+        assert_eq!(interp.input_read, 1);
+    }
+
+    #[test]
+    fn comma_at_eof_follows_the_eof_behavior() {
+        for (eof, expected) in [
+            (EofBehavior::Unchanged, 7),
+            (EofBehavior::Zero, 0),
+            (EofBehavior::Max, 255),
+        ] {
+            let mut interp = BFInt::new();
+            interp.extend_prog(b",").unwrap();
+            interp.eof_behavior = eof;
+            interp.mem[0] = 7;
+            assert_eq!(interp.step(), StepOutcome::Halted);
+            assert_eq!(interp.mem[0], expected, "{eof:?}");
+        }
+    }
+
+    #[test]
+    fn comma_at_eof_waits_for_input() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b",").unwrap();
+        assert_eq!(interp.step(), StepOutcome::NeedsInput);
+        assert_eq!((interp.state, interp.prog_ptr), (InterpState::Waiting, 0));
+
+        interp.push_input(b"B");
+        assert_eq!(interp.state, InterpState::Running);
+        interp.step();
+        assert_eq!(interp.mem[0], 66);
+    }
+
+    #[test]
+    fn loops_added_after_stepping_use_absolute_offsets() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+++").unwrap();
+        interp.run_with_limit(3);
+        interp.extend_prog(b">+[<->-]").unwrap();
+
+        assert_eq!(interp.loop_map, vec![(5, 10)]);
+        assert_eq!(interp.matching_bracket(5), Some(10));
+        assert_eq!(interp.matching_bracket(10), Some(5));
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Halted);
+        assert_eq!(&interp.mem[..2], &[2, 0]);
+    }
+
+    #[test]
+    fn moving_right_past_the_tape_grows_it() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b">").unwrap();
+        interp.mem_ptr = 999;
+        interp.step();
+        assert_eq!(interp.mem_ptr, 1000);
+        assert_eq!(interp.mem.len(), 1001);
+        assert_eq!(interp.mem[1000], 0);
+    }
+
+    #[test]
+    fn reset_zeroes_the_tape_and_keeps_the_program() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++[>+++<-]>.").unwrap();
+        interp.push_input(b"x");
+        interp.run_with_limit(100);
+        interp.reset();
+
+        assert_eq!((interp.prog_ptr, interp.mem_ptr), (0, 0));
+        assert!(interp.mem.iter().all(|&v| v == 0));
+        assert_eq!(interp.mem.len(), 1000);
+        assert_eq!(interp.prog, b"++[>+++<-]>.");
+        assert_eq!(interp.loop_map, vec![(2, 9)]);
+        assert!(interp.output().is_empty() && interp.input.is_empty());
+        assert_eq!(interp.state, InterpState::Running);
+    }
+
+    #[test]
+    fn visit_limit_counts_across_resumed_calls() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+[]").unwrap();
+        interp.visit_limit = Some(8);
+        interp.begin_run();
+        assert_eq!(interp.resume_run(4), StepOutcome::Continue);
+        assert_eq!(interp.resume_run(4), StepOutcome::Continue);
+        assert_eq!(interp.resume_run(4), StepOutcome::VisitLimit(2));
+    }
+
+    #[test]
+    fn begin_run_starts_the_visit_count_over() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+[]").unwrap();
+        interp.visit_limit = Some(8);
+        interp.begin_run();
+        assert_eq!(interp.resume_run(8), StepOutcome::Continue);
+        interp.begin_run();
+        assert_eq!(interp.resume_run(8), StepOutcome::Continue);
+    }
+
+    #[test]
+    fn lone_brackets_are_rejected() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+").unwrap();
+        assert_eq!(
+            interp.extend_prog(b"+]"),
+            Err(BracketError::UnmatchedClose(2))
+        );
+        assert_eq!(
+            interp.extend_prog(b"[+"),
+            Err(BracketError::UnmatchedOpen(1))
+        );
+        assert_eq!(interp.prog, b"+");
+    }
+
+    #[test]
+    fn nested_loops_are_matched() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"[[]]").unwrap();
+        assert_eq!(interp.matching_bracket(0), Some(3));
+        assert_eq!(interp.matching_bracket(1), Some(2));
+    }
+
+    #[test]
+    fn a_loop_can_be_split_across_snippets() {
+        let mut interp = BFInt::new();
+        assert_eq!(interp.extend_prog_partial(b"+["), Ok(false));
+        assert!(interp.prog.is_empty());
+        assert_eq!(interp.extend_prog_partial(b"-]"), Ok(true));
+        assert_eq!(interp.prog, b"+[-]");
+        assert_eq!(interp.matching_bracket(1), Some(3));
+    }
+
+    #[test]
+    fn a_stray_close_is_rejected_while_a_loop_is_held_back() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+").unwrap();
+        assert_eq!(interp.extend_prog_partial(b"[-"), Ok(false));
+        assert_eq!(
+            interp.extend_prog_partial(b"]]"),
+            Err(BracketError::UnmatchedClose(4))
+        );
+        assert_eq!(interp.extend_prog_partial(b"]"), Ok(true));
+        assert_eq!(interp.prog, b"+[-]");
+    }
+
+    #[test]
+    fn run_with_limit_halts_a_finite_program() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++[->+<]").unwrap();
+        assert_eq!(interp.run_with_limit(1000), StepOutcome::Halted);
+        assert_eq!(&interp.mem[..2], [0, 2]);
+    }
+
+    #[test]
+    fn run_with_limit_stops_an_endless_loop_at_the_bound() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+[]").unwrap();
+        assert_eq!(interp.run_with_limit(50), StepOutcome::Continue);
+        assert_eq!(interp.step_count, 50);
+        assert_eq!(interp.state, InterpState::Running);
+    }
+
+    #[test]
+    fn changing_the_eof_policy_resumes_a_waiting_read() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+,").unwrap();
+        interp.run_with_limit(10);
+        assert_eq!(interp.state, InterpState::Waiting);
+        interp.set_eof_behavior(EofBehavior::Zero);
+        assert_eq!(interp.run_with_limit(10), StepOutcome::Halted);
+        assert_eq!(interp.mem[0], 0);
+    }
+
+    #[test]
+    fn trace_csv_escapes_quotes_in_comments() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+\"").unwrap();
+        interp.enable_trace(10);
+        interp.run_with_limit(10);
+        let csv = interp.trace_csv().unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "step,pc,opcode,mem_ptr,cell_after");
+        assert_eq!(rows[1].split(',').nth(2), Some("\"+\""));
+        assert_eq!(rows[2].split(',').nth(2), Some("\"\"\"\""));
+    }
+
+    #[test]
+    fn run_steps_runs_the_requested_count() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+[]").unwrap();
+        let (steps, _, outcome) = interp.run_steps(500);
+        assert_eq!((steps, outcome), (500, StepOutcome::Continue));
+    }
+
+    #[test]
+    fn run_steps_stops_early_when_the_program_halts() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++.").unwrap();
+        let (steps, output, outcome) = interp.run_steps(500);
+        assert_eq!((steps, outcome), (3, StepOutcome::Halted));
+        assert_eq!(output, vec![2]);
+        assert_eq!(interp.state, InterpState::Finished);
+    }
+
+    #[test]
+    fn breakpoint_inside_a_loop_stops_each_iteration() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+++[>+<-]").unwrap();
+        interp.breakpoints.insert(5);
+
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Breakpoint(5));
+        assert_eq!((interp.prog_ptr, interp.mem[1]), (5, 0));
+        // resuming runs the instruction under the breakpoint first
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Breakpoint(5));
+        assert_eq!(interp.mem[1], 1);
+
+        interp.breakpoints.clear();
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Halted);
+        assert_eq!(&interp.mem[..2], &[0, 3]);
+    }
+
+    #[test]
+    fn plus_wraps_at_each_cell_width() {
+        for (width, max) in [
+            (CellWidth::U8, 255),
+            (CellWidth::U16, 65_535),
+            (CellWidth::U32, u32::MAX),
+        ] {
+            let mut interp = BFInt::with_cell_width(width);
+            interp.extend_prog(b"+").unwrap();
+            interp.mem[0] = max;
+            interp.step();
+            assert_eq!(interp.mem[0], 0, "{width:?}");
         }
     }
+
+    #[test]
+    fn saturating_plus_stops_at_u32_max() {
+        let mut interp = BFInt::with_cell_width(CellWidth::U32);
+        interp.extend_prog(b"+").unwrap();
+        interp.arithmetic = ArithmeticMode::Saturating;
+        interp.mem[0] = u32::MAX;
+        interp.step();
+        assert_eq!(interp.mem[0], u32::MAX);
+    }
+
+    #[test]
+    fn events_are_emitted_in_step_order() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+>.").unwrap();
+        interp.enable_events();
+        interp.run_with_limit(10);
+        assert_eq!(
+            interp.take_events(),
+            [
+                Event::Write { cell: 0, value: 1 },
+                Event::Move(1),
+                Event::Output(0)
+            ]
+        );
+        assert!(interp.take_events().is_empty());
+    }
+
+    #[test]
+    fn run_to_stops_at_the_end_offset() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+++++").unwrap();
+        assert_eq!(interp.run_to(3, 100), StepOutcome::Continue);
+        assert_eq!((interp.prog_ptr, interp.mem[0]), (3, 3));
+    }
+
+    #[test]
+    fn run_to_stops_on_pausing_outcomes() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+++++").unwrap();
+        interp.breakpoints.insert(2);
+        assert_eq!(interp.run_to(5, 100), StepOutcome::Breakpoint(2));
+        assert_eq!(interp.mem[0], 2);
+
+        let mut interp = BFInt::new();
+        interp.extend_prog(b">>>>").unwrap();
+        interp.pointer_limit = Some(2);
+        assert_eq!(interp.run_to(4, 100), StepOutcome::PointerLimit(3));
+        assert_eq!(interp.prog_ptr, 3);
+    }
+
+    #[test]
+    fn run_stops_when_the_pc_cannot_advance() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+,+").unwrap();
+        interp.run();
+        assert_eq!((interp.prog_ptr, interp.state), (1, InterpState::Waiting));
+
+        interp.push_input(b"A");
+        interp.run();
+        assert_eq!((interp.mem[0], interp.state), (66, InterpState::Finished));
+    }
+
+    #[test]
+    fn a_reset_program_runs_again_the_same_way() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++[>+++<-]>.").unwrap();
+        interp.run_with_limit(100);
+        let first = interp.take_output();
+
+        interp.reset();
+        assert_eq!(interp.state, InterpState::Running);
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Halted);
+        assert_eq!(interp.take_output(), first);
+        assert_eq!(&interp.mem[..2], &[0, 6]);
+    }
+
+    #[test]
+    fn reset_drops_a_half_entered_snippet() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+").unwrap();
+        assert_eq!(interp.extend_prog_partial(b"[-"), Ok(false));
+        interp.reset();
+
+        assert_eq!(interp.extend_prog_partial(b">"), Ok(true));
+        assert_eq!(interp.prog, b"+>");
+    }
+
+    #[test]
+    fn left_of_cell_zero_is_reported_without_moving() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+<").unwrap();
+        interp.step();
+        assert_eq!(interp.step(), StepOutcome::PointerUnderflow);
+        assert_eq!((interp.prog_ptr, interp.mem_ptr), (1, 0));
+        assert_eq!(interp.state, InterpState::Running);
+        assert_eq!(interp.mem[0], 1);
+    }
+
+    #[test]
+    fn step_back_drops_the_undone_step_from_the_trace() {
+        let mut interp = BFInt::new();
+        interp.enable_trace(10);
+        interp.extend_prog(b"+>+").unwrap();
+        interp.run_with_limit(10);
+        interp.step_back();
+        interp.step_back();
+        let steps: Vec<u64> = interp
+            .trace
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|e| e.step)
+            .collect();
+        assert_eq!(steps, [1]);
+
+        interp.run_with_limit(10);
+        let steps: Vec<u64> = interp
+            .trace
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|e| e.step)
+            .collect();
+        assert_eq!(steps, [1, 2, 3]);
+    }
+
+    #[test]
+    fn a_nested_loop_jumps_correctly_many_times() {
+        let mut interp = BFInt::with_cell_width(CellWidth::U32);
+        interp
+            .extend_prog(b"++++++++++[>++++++++++[>++++++++++[>+>++<<-]<-]<-]")
+            .unwrap();
+        assert_eq!(interp.run_with_limit(1_000_000), StepOutcome::Halted);
+        assert_eq!(&interp.mem[..5], &[0, 0, 0, 1000, 2000]);
+        // each `[` runs once per entry into its loop, each `]` once per iteration
+        let brackets: Vec<u64> = interp
+            .prog
+            .iter()
+            .zip(&interp.pc_counts)
+            .filter(|&(&b, _)| b == b'[' || b == b']')
+            .map(|(_, &count)| count)
+            .collect();
+        assert_eq!(brackets, [1, 10, 100, 1000, 100, 10]);
+    }
+
+    #[test]
+    fn dot_buffers_the_output_byte() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++++++++[>++++++++<-]>+.").unwrap();
+        interp.run_with_limit(1000);
+        assert_eq!(interp.output(), b"A");
+        assert_eq!(interp.take_output(), [65]);
+        assert!(interp.output().is_empty());
+    }
+
+    #[test]
+    fn step_back_restores_each_earlier_state_exactly() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+>,<-.>").unwrap();
+        interp.push_input(b"z");
+        let state = |interp: &BFInt| {
+            (
+                interp.prog_ptr,
+                interp.mem_ptr,
+                interp.mem.clone(),
+                interp.input.clone(),
+            )
+        };
+
+        let mut states = vec![state(&interp)];
+        for _ in 0..6 {
+            interp.step();
+            states.push(state(&interp));
+        }
+        states.pop();
+        while let Some(expected) = states.pop() {
+            assert!(interp.step_back());
+            assert_eq!(state(&interp), expected);
+        }
+        assert!(!interp.step_back());
+        assert_eq!(interp.step_count, 0);
+    }
+
+    #[test]
+    fn annotate_shifts_offsets_without_changing_the_run() {
+        let prog = b"++[->+<]";
+        let mut plain = BFInt::new();
+        plain.extend_prog(prog).unwrap();
+        plain.run_with_limit(100);
+
+        let mut interp = BFInt::new();
+        interp.extend_prog(prog).unwrap();
+        interp.run_steps(3);
+        interp.breakpoints.insert(5);
+        // the comma is an instruction and is left out of the note
+        assert_eq!(interp.annotate(2, "move it, right"), 13);
+        assert_eq!(&interp.prog[..17], b"++move it right[-");
+        assert_eq!(interp.prog_ptr, 3 + 13);
+        assert_eq!(interp.breakpoints, HashSet::from([5 + 13]));
+        assert_eq!(interp.matching_bracket(15), Some(20));
+
+        let instructions: Vec<u8> = interp
+            .prog
+            .iter()
+            .copied()
+            .filter(|&b| is_instruction(b))
+            .collect();
+        assert_eq!(instructions, prog);
+        interp.breakpoints.clear();
+        assert_eq!(interp.run_with_limit(100), StepOutcome::Halted);
+        assert_eq!(interp.mem[..2], plain.mem[..2]);
+        assert_eq!(interp.step_count, plain.step_count);
+    }
+
+    #[test]
+    fn step_back_over_a_loop_jump_returns_to_the_bracket() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"++[-]+").unwrap();
+        interp.run_steps(4);
+        assert_eq!((interp.prog_ptr, interp.mem[0]), (4, 1));
+
+        // the `]` is taken back to the loop body
+        interp.step();
+        let jumped_to = interp.prog_ptr;
+        assert!(jumped_to < 4);
+        assert!(interp.step_back());
+        assert_eq!((interp.prog_ptr, interp.mem[0]), (4, 1));
+        interp.step();
+        assert_eq!(interp.prog_ptr, jumped_to);
+
+        // and a `[` that skipped its loop comes back to the `[`
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"[+]+").unwrap();
+        interp.step();
+        assert_eq!(interp.prog_ptr, 3);
+        assert!(interp.step_back());
+        assert_eq!(interp.prog_ptr, 0);
+        assert_eq!(interp.step_count, 0);
+    }
+
+    #[test]
+    fn rewind_to_iteration_start_goes_back_to_the_current_iteration() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+++[>++<-]").unwrap();
+        assert_eq!(interp.rewind_to_iteration_start(), None);
+
+        // into the second iteration, where the body starts at offset 4
+        interp.run_steps(3 + 7);
+        assert_eq!(interp.prog_ptr, 4);
+        assert_eq!(interp.rewind_to_iteration_start(), Some(0));
+        let start = (interp.mem[..2].to_vec(), interp.mem_ptr, interp.step_count);
+        assert_eq!(start, (vec![2, 2], 0, 10));
+
+        interp.run_steps(3);
+        assert_eq!(interp.rewind_to_iteration_start(), Some(3));
+        assert_eq!(interp.prog_ptr, 4);
+        assert_eq!(
+            (interp.mem[..2].to_vec(), interp.mem_ptr, interp.step_count),
+            start
+        );
+    }
 }
@@ -1,22 +1,35 @@
 use crate::{
-    interpreter::{BFInt, InterpState},
+    condition::CellCondition,
+    examples::EXAMPLES,
+    interpreter::*,
     popup::*,
     theme::THEME,
-    widgets::TextEntry,
+    widgets::{
+        apply_carriage_returns, cell_width, cells_per_row, display_column, display_width,
+        format_cell, hex_dump, marker_column, memory_first_row, program_scroll, MemoryView,
+        TextEntry,
+    },
 };
-use crossterm::event::{self, KeyCode};
+use crossterm::event::{self, KeyCode, KeyModifiers};
+use itertools::Itertools;
 use ratatui::{
     layout::Offset,
     prelude::*,
     widgets::{Block, BorderType, Paragraph, Widget},
 };
 use std::{
+    cell::Cell,
+    collections::HashMap,
     fmt,
     io::{self},
 };
 
+mod condition;
+mod examples;
+mod headless;
 mod interpreter;
 mod popup;
+mod state;
 mod theme;
 mod tui;
 mod widgets;
@@ -32,17 +45,62 @@ enum RunningMode {
     Exiting,
 }
 
+// how the canvas above the program view is shared between the panels
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum PanelLayout {
+    #[default]
+    Split, // repl on the left, memory over info on the right
+    NoInfo,     // repl on the left, memory fills the right column
+    MemoryOnly, // memory fills the canvas
+}
+
+impl PanelLayout {
+    fn cycle_next(&mut self) {
+        *self = match self {
+            PanelLayout::Split => PanelLayout::NoInfo,
+            PanelLayout::NoInfo => PanelLayout::MemoryOnly,
+            PanelLayout::MemoryOnly => PanelLayout::Split,
+        }
+    }
+
+    fn shows_info(self) -> bool {
+        self == PanelLayout::Split
+    }
+
+    // [repl, memory, info], hidden panels get an empty rect
+    fn areas(self, canvas: Rect) -> [Rect; 3] {
+        let horizontal =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)]);
+        let [repl_area, mem_info_area] = horizontal.areas(canvas);
+        match self {
+            PanelLayout::Split => {
+                let vertical =
+                    Layout::vertical([Constraint::Percentage(75), Constraint::Percentage(25)]);
+                let [mem_area, info_area] = vertical.areas(mem_info_area);
+                [repl_area, mem_area, info_area]
+            }
+            PanelLayout::NoInfo => [repl_area, mem_info_area, Rect::default()],
+            PanelLayout::MemoryOnly => [Rect::default(), canvas, Rect::default()],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Mode {
     Normal,
     Editing,
     Command,
+    Input,
 }
 
+#[derive(PartialEq)]
 enum Dialogue {
     None,
     Save,
-    NewTask,
+    Message,
+    Quit,
+    Reset,
+    Examples,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -76,20 +134,226 @@ impl ReplType {
     }
 }
 
+struct ReplLine {
+    kind: ReplType,
+    text: String,
+}
+
+impl ReplLine {
+    fn new(kind: ReplType, text: String) -> Self {
+        ReplLine { kind, text }
+    }
+}
+
+// option names accepted by `:set`, used for tab completion
+const SET_OPTIONS: [&str; 17] = [
+    "eof",
+    "arith",
+    "cellwidth",
+    "output",
+    "frozen",
+    "comments",
+    "marker",
+    "tapelimit",
+    "dim",
+    "confirmquit",
+    "errors",
+    "tabwidth",
+    "ptrlimit",
+    "resetkey",
+    "cr",
+    "visitlimit",
+    "outpanel",
+];
+
+// Normal mode keys, the first COMMON_KEYS are also shown in the bottom bar
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("q", "Quit"),
+    ("n", "Step"),
+    ("c", "Continue"),
+    (":", "Command"),
+    ("?", "Help"),
+    ("b", "Step back"),
+    ("p", "Run/pause"),
+    ("e", "Edit program"),
+    ("i", "Queue input"),
+    ("m", "Cycle memory view"),
+    ("l", "Cycle layout"),
+    ("+", "Increment cell"),
+    ("-", "Decrement cell"),
+    ("Up/Down", "Scroll the REPL"),
+    ("PgUp/PgDn", "Page the memory panel"),
+    ("ctrl-r", "Reset (see :set resetkey)"),
+];
+const COMMON_KEYS: usize = 5;
+
+// commands listed by :help
+const COMMANDS: &[(&str, &str)] = &[
+    ("help", "show this help"),
+    ("quit, q / quit!, q!", "quit"),
+    ("load <path>", "append a program file"),
+    ("save [path]", "write the program to a file"),
+    ("examples [name]", "list or load a bundled program"),
+    ("eval <code>", "append code and run it"),
+    ("input <text>", "queue input for ,"),
+    ("feedcell", "queue the current cell as input"),
+    (
+        "run, runin / run!",
+        "run until input (ignoring breakpoints)",
+    ),
+    ("continue, c", "resume a paused run"),
+    ("step <n>", "run n instructions"),
+    ("speed <n>", "auto-run steps per frame, 0 pauses"),
+    ("nextout", "run to the next output"),
+    ("dryrun <n>", "preview n steps without running them"),
+    ("replaysteps <n>", "rewind n steps and replay them"),
+    ("loopback", "rewind to the start of the loop iteration"),
+    ("reset", "restart the program and clear the REPL"),
+    ("clear-repl", "clear the transcript"),
+    ("break, unbreak <offset>", "toggle a breakpoint"),
+    ("cbreak <cond> / clear", "break when a cell condition holds"),
+    ("watch, unwatch <cell>", "break when a cell changes"),
+    ("freeze, unfreeze <cell>", "protect a cell from writes"),
+    ("poke <cell>=<value>,.. [run]", "set cells"),
+    ("tape load <path> [ptr]", "load the tape from a file"),
+    ("expect tape <path>", "compare the tape against a file"),
+    ("snap save|restore|del|list", "named snapshots"),
+    (
+        "state json [path] / load <path>",
+        "dump or load the state as json",
+    ),
+    ("trace on|off|export", "record executed instructions"),
+    ("record start <path> / stop", "write a trace of every frame"),
+    ("layout split|noinfo|memory", "arrange the panels"),
+    ("memview dec|hex|bin|glyph|ascii", "memory panel format"),
+    ("set <option> <value>", "change an option, tab completes"),
+    ("name <text>", "label the session"),
+    ("progdiff <path>", "diff instructions against a file"),
+    ("hot [n]", "most executed instructions"),
+    ("hash", "hash of the instructions"),
+    ("format [write]", "show or apply an indented program"),
+    ("match <offset>", "find the matching bracket"),
+    ("loopinfo <offset>", "cells a loop reads and writes"),
+    ("extent", "cells the program can reach"),
+    ("reachable", "instructions reachable from the pc"),
+    ("outsize", "bytes the program will output"),
+    ("annotate <offset> <note>", "insert a comment"),
+    ("outmap <byte> <text>", "show a byte as text in output"),
+    ("inputview", "show the input queue"),
+    ("ranges on|off", "track min and max per cell"),
+];
+
+// `x`, `ctrl-x` or `alt-x` as a key and its modifiers
+fn parse_key(text: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let (modifiers, key) = match text.split_once('-') {
+        Some(("ctrl", key)) => (KeyModifiers::CONTROL, key),
+        Some(("alt", key)) => (KeyModifiers::ALT, key),
+        Some(_) => return None,
+        None => (KeyModifiers::NONE, text),
+    };
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some((KeyCode::Char(c), modifiers)),
+        _ => None,
+    }
+}
+
+// splits on spaces, text inside double quotes stays together as one argument
+fn split_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            ' ' if !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
 struct Options {
     error_display_time: u32,
     refresh_rate: u32,
+    run_step_limit: usize,
+    trace_capacity: usize,
+    memory_view: MemoryView,
+    format_keep_comments: bool,
+    pointer_marker: bool,
+    dim_executed: bool,
+    confirm_quit_key: bool,
+    tab_width: usize,
+    error_popup: bool,
+    replay_delay_frames: u32,
+    layout: PanelLayout,
+    reset_key: (KeyCode, KeyModifiers),
+    carriage_return: bool,
+    output_panel: bool,
+    // instructions run each frame while auto-running
+    steps_per_frame: u64,
 }
 
 pub struct App {
     mode: Mode,
     running_mode: RunningMode,
     options: Options,
-    lines: Vec<ReplType>,
+    lines: Vec<ReplLine>,
+    repl_scroll: usize,
+    // pages the memory panel is scrolled away from the pointer, 0 follows it
+    memory_pages: isize,
+    // (cells per row, visible rows) of the last drawn memory panel, so paging
+    // can stop at either end of the tape
+    memory_grid: Cell<(usize, usize)>,
     interp: BFInt,
 
+    program_mark: Option<usize>,
+    recording: Option<String>,
+    snapshots: HashMap<String, Snapshot>,
+    // shown next to the app name, purely cosmetic
+    session_name: Option<String>,
+    // program output when it goes to its own panel instead of the transcript
+    output_lines: Vec<String>,
+    // output bytes since the last `\n`, and the index of the line showing them
+    partial_output: Vec<u8>,
+    partial_line: Option<usize>,
+    // stepping the program a few instructions every frame
+    auto_run: bool,
+    // steps left to re-run for :replaysteps, and frames until the next one
+    replay_remaining: usize,
+    replay_timer: u32,
+    dialogue: Dialogue,
+    message_popup: MessagePopup,
+    confirm_popup: ConfirmationPopup,
+    save_popup: TextEntryPopup,
+    examples_popup: ListPopup,
+
     command_field: TextEntry,
+    // entered commands, oldest first, and the one Up/Down is showing
+    command_history: Vec<String>,
+    history_pos: Option<usize>,
+    input_field: TextEntry,
+    edit_field: TextEntry,
     error_str: String,
+    error_is_info: bool,
     frames_since_error: Option<u32>,
 }
 
@@ -102,34 +366,47 @@ impl Widget for &App {
             Constraint::Length(1),
         ]);
         let [title_bar_area, canvas_area, program_area, bottom_bar_area] = vertical.areas(area);
-        let horizontal =
-            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)]);
-        let [repl_area, mem_info_area] = horizontal.areas(canvas_area);
-        let vertical = Layout::vertical([Constraint::Percentage(75), Constraint::Percentage(25)]);
-        let [mem_area, info_area] = vertical.areas(mem_info_area);
+        let [repl_area, mem_area, info_area] = self.options.layout.areas(canvas_area);
 
         //Block::new().style(THEME.root).render(area, buf);
 
         self.render_title_bar(title_bar_area, buf);
 
-        Paragraph::new(
-            self.lines
-                .iter()
-                .map(|l| Line::from(l.as_str()))
-                .collect::<Vec<Line>>(),
-        )
-        .block(
-            Block::bordered()
-                .border_style(THEME.root)
-                .title("REPL")
-                .title_style(THEME.root)
-                .style(THEME.root)
-                .border_type(BorderType::Rounded),
-        )
-        .render(repl_area, buf);
+        if !repl_area.is_empty() {
+            // keep the newest lines in view, repl_scroll counts lines scrolled back from the end
+            let visible = repl_area.height.saturating_sub(2) as usize;
+            let end = self.lines.len() - self.repl_scroll.min(self.lines.len());
+            let start = end.saturating_sub(visible);
+            Paragraph::new(
+                self.lines[start..end]
+                    .iter()
+                    .map(|l| {
+                        Line::from(vec![
+                            Span::from(l.kind.as_str()),
+                            Span::from(" "),
+                            Span::from(l.text.as_str()),
+                        ])
+                    })
+                    .collect::<Vec<Line>>(),
+            )
+            .block(
+                Block::bordered()
+                    .border_style(THEME.root)
+                    .title("REPL")
+                    .title_style(THEME.root)
+                    .style(THEME.root)
+                    .border_type(BorderType::Rounded),
+            )
+            .render(repl_area, buf);
+        }
 
-        // change to be slice of current program sized ot fit
-        Paragraph::new(unsafe { String::from_utf8_unchecked(self.interp.prog.clone()) })
+        // scrolled sideways so the instruction at prog_ptr stays in view
+        let tab_width = self.options.tab_width;
+        let view_width = program_area.width.saturating_sub(2) as usize;
+        let caret = display_column(&self.interp.prog, self.interp.prog_ptr, tab_width);
+        let scroll = program_scroll(caret, view_width);
+        Paragraph::new(Line::from(self.program_spans()))
+            .scroll((0, scroll as u16))
             .block(
                 Block::bordered()
                     .border_style(THEME.root)
@@ -141,33 +418,31 @@ impl Widget for &App {
             .render(program_area, buf);
         Span::from("^").render(
             program_area.offset(Offset {
-                x: self.interp.prog_ptr as i32 + 1,
+                x: (caret - scroll) as i32 + 1,
                 y: 2,
             }),
             buf,
         );
+        if let Some(mark) = self.program_mark {
+            let column = display_column(&self.interp.prog, mark, tab_width);
+            if (scroll..scroll + view_width).contains(&column) {
+                Span::from("^").style(THEME.program_mark).render(
+                    program_area.offset(Offset {
+                        x: (column - scroll) as i32 + 1,
+                        y: 2,
+                    }),
+                    buf,
+                );
+            }
+        }
 
-        Paragraph::new(format!("{:?}", self.interp.mem))
-            .block(
-                Block::bordered()
-                    .border_style(THEME.root)
-                    .title("Memory")
-                    .title_style(THEME.root)
-                    .style(THEME.root)
-                    .border_type(BorderType::Rounded),
-            )
-            .render(mem_area, buf);
-
-        Paragraph::new("memory usage: 17 bytes (2 pages)")
-            .block(
-                Block::bordered()
-                    .border_style(THEME.root)
-                    .title("Info")
-                    .title_style(THEME.root)
-                    .style(THEME.root)
-                    .border_type(BorderType::Rounded),
-            )
-            .render(info_area, buf);
+        self.render_memory(mem_area, buf);
+        // the output panel takes over the info area
+        if self.output_in_panel() {
+            self.render_output_panel(info_area, buf);
+        } else if !info_area.is_empty() {
+            self.render_info(info_area, buf);
+        }
 
         if self.mode == Mode::Command {
             Line::from(vec![
@@ -182,24 +457,121 @@ impl Widget for &App {
                 }),
                 buf,
             );
-        } else if let Some(_) = self.frames_since_error {
+        } else if self.mode == Mode::Editing {
+            Line::from(vec![
+                Span::from(ReplType::Code.as_str()),
+                Span::from(" "),
+                Span::from(self.edit_field.get_str()),
+            ])
+            .render(bottom_bar_area, buf);
+            Span::from("█").render(
+                bottom_bar_area.offset(Offset {
+                    x: 5 + self.edit_field.get_cursor_pos() as i32,
+                    y: 0,
+                }),
+                buf,
+            );
+        } else if self.mode == Mode::Input {
+            Line::from(vec![
+                Span::from("in> "),
+                Span::from(self.input_field.get_str()),
+            ])
+            .render(bottom_bar_area, buf);
+            Span::from("█").render(
+                bottom_bar_area.offset(Offset {
+                    x: 4 + self.input_field.get_cursor_pos() as i32,
+                    y: 0,
+                }),
+                buf,
+            );
+        } else if self.frames_since_error.is_some() && self.error_is_info {
+            Span::from(self.error_str.as_str())
+                .style(THEME.root)
+                .render(bottom_bar_area, buf);
+        } else if self.frames_since_error.is_some() {
             Span::from(format!("Error: {}", self.error_str))
                 .style(THEME.command_error)
                 .render(bottom_bar_area, buf);
         } else {
             self.render_bottom_bar(bottom_bar_area, buf);
         }
+
+        match self.dialogue {
+            Dialogue::Message => self.message_popup.render(canvas_area, buf),
+            Dialogue::Quit | Dialogue::Reset => self.confirm_popup.render(canvas_area, buf),
+            Dialogue::Save => self.save_popup.render(canvas_area, buf),
+            Dialogue::Examples => self.examples_popup.render(canvas_area, buf),
+            _ => {}
+        }
     }
 }
 
 impl App {
+    fn new() -> App {
+        App {
+            mode: Mode::Normal,
+            running_mode: RunningMode::Running,
+            options: Options {
+                error_display_time: 2,
+                refresh_rate: 60,
+                run_step_limit: 100_000,
+                trace_capacity: 10_000,
+                memory_view: MemoryView::Decimal,
+                format_keep_comments: false,
+                pointer_marker: true,
+                dim_executed: true,
+                confirm_quit_key: false,
+                tab_width: 4,
+                error_popup: false,
+                replay_delay_frames: 6,
+                layout: PanelLayout::Split,
+                reset_key: (KeyCode::Char('r'), KeyModifiers::CONTROL),
+                carriage_return: false,
+                output_panel: false,
+                steps_per_frame: 10,
+            },
+            lines: Vec::new(),
+            repl_scroll: 0,
+            memory_pages: 0,
+            memory_grid: Cell::new((1, 1)),
+            interp: BFInt::new(),
+            program_mark: None,
+            recording: None,
+            snapshots: HashMap::new(),
+            session_name: None,
+            output_lines: Vec::new(),
+            partial_output: Vec::new(),
+            partial_line: None,
+            auto_run: false,
+            replay_remaining: 0,
+            replay_timer: 0,
+            dialogue: Dialogue::None,
+            message_popup: MessagePopup::default(),
+            examples_popup: ListPopup::default(),
+            confirm_popup: ConfirmationPopup::new(String::new(), String::new()),
+            save_popup: TextEntryPopup::new(" Save as ".to_string(), 1),
+            command_field: TextEntry::default(),
+            command_history: Vec::new(),
+            history_pos: None,
+            input_field: TextEntry::default(),
+            edit_field: TextEntry::default(),
+            error_str: String::new(),
+            error_is_info: false,
+            frames_since_error: None,
+        }
+    }
+
     pub fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
         // initialization
         self.command_field.set_text("t load".to_string());
         self.process_command();
 
         self.interp.mem[0] = 7;
-        self.interp.extend_prog(b"[->+<]");
+        self.interp
+            .extend_prog(b"[->+<]")
+            .expect("the starting program is balanced");
+        self.lines
+            .push(ReplLine::new(ReplType::Code, "[->+<]".to_string()));
 
         // main loop
         while self.running_mode != RunningMode::Exiting {
@@ -215,7 +587,10 @@ impl App {
                 }
             }
 
-            // popup handler
+            self.run_frame();
+            self.replay_frame();
+
+            self.update_dialogue();
         }
 
         // clean up
@@ -224,40 +599,158 @@ impl App {
         Ok(())
     }
 
+    // closes a popup once it has been answered and acts on the answer
+    fn update_dialogue(&mut self) {
+        match self.dialogue {
+            Dialogue::Message if self.message_popup.status != PopupStatus::InUse => {
+                self.message_popup.close();
+                self.dialogue = Dialogue::None;
+            }
+            Dialogue::Quit if self.confirm_popup.status != PopupStatus::InUse => {
+                if self.confirm_popup.status == PopupStatus::Confirmed
+                    && self.confirm_popup.decision()
+                {
+                    self.force_quit();
+                }
+                self.confirm_popup.close();
+                self.dialogue = Dialogue::None;
+            }
+            Dialogue::Reset if self.confirm_popup.status != PopupStatus::InUse => {
+                if self.confirm_popup.status == PopupStatus::Confirmed
+                    && self.confirm_popup.decision()
+                {
+                    self.reset_session();
+                }
+                self.confirm_popup.close();
+                self.dialogue = Dialogue::None;
+            }
+            Dialogue::Save if self.save_popup.status != PopupStatus::InUse => {
+                let confirmed = self.save_popup.status == PopupStatus::Confirmed;
+                let path = self.save_popup.take();
+                self.save_popup.reset();
+                self.dialogue = Dialogue::None;
+                if confirmed {
+                    self.save_file(&path);
+                }
+            }
+            Dialogue::Examples if self.examples_popup.status != PopupStatus::InUse => {
+                let chosen = self.examples_popup.status == PopupStatus::Confirmed;
+                self.examples_popup.close();
+                self.dialogue = Dialogue::None;
+                if chosen {
+                    self.load_example(self.examples_popup.selected);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn render_frame(&self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
         if event::poll(std::time::Duration::from_millis(16))? {
-            if let event::Event::Key(key) = event::read()? {
+            let event = event::read()?;
+            if let event::Event::Paste(text) = &event {
+                self.paste(text);
+            }
+            if let event::Event::Key(key) = event {
                 // key holds info about modifiers (shitf, ctrl, alt)
-                if key.kind == event::KeyEventKind::Press {
-                    if !self.dispatch_input(key.code) {
-                        match key.code {
-                            KeyCode::Char('q') => self.try_quit(),
-                            KeyCode::Char('n') => self.interp.step(),
-                            KeyCode::Char(':') => {
-                                self.mode = Mode::Command;
-                                self.frames_since_error = None;
-                                self.command_field.clear();
-                            }
-                            _ => {}
-                        }
-                    }
+                if key.kind == event::KeyEventKind::Press
+                    && !self.dispatch_input(key.code, key.modifiers)
+                {
+                    self.normal_key(key.code, key.modifiers);
                 }
             }
         }
         Ok(())
     }
 
-    fn dispatch_input(&mut self, key: KeyCode) -> bool {
-        if self.mode == Mode::Command {
+    // keys that no popup or text field took
+    fn normal_key(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        if (key, modifiers) == self.options.reset_key {
+            self.reset();
+            return;
+        }
+        match key {
+            KeyCode::Char('q') if self.options.confirm_quit_key => {
+                self.confirm_popup.title = " Quit ".to_string();
+                self.confirm_popup.body = "Quit BFRepl?".to_string();
+                self.confirm_popup.show();
+                self.dialogue = Dialogue::Quit;
+            }
+            KeyCode::Char('q') => self.try_quit(),
+            KeyCode::Char('n') => self.step_once(),
+            KeyCode::Char('b') => self.step_back_once(),
+            KeyCode::Char('c') => self.continue_run(),
+            KeyCode::Char('?') => self.show_help(),
+            KeyCode::Char('p') => self.toggle_auto_run(),
+            KeyCode::Up => self.repl_scroll = (self.repl_scroll + 1).min(self.lines.len()),
+            KeyCode::Down => self.repl_scroll = self.repl_scroll.saturating_sub(1),
+            KeyCode::Char('m') => self.options.memory_view.cycle_next(),
+            KeyCode::PageUp => self.page_memory(-1),
+            KeyCode::PageDown => self.page_memory(1),
+            KeyCode::Char('l') => {
+                let mut layout = self.options.layout;
+                layout.cycle_next();
+                self.set_layout(layout);
+            }
+            KeyCode::Char('+') => self.interp.adjust_cell(true),
+            KeyCode::Char('-') => self.interp.adjust_cell(false),
+            // queue input ahead of the `,` that will read it
+            KeyCode::Char('i') => {
+                self.mode = Mode::Input;
+                self.frames_since_error = None;
+                self.input_field.clear();
+            }
+            KeyCode::Char('e') => {
+                self.mode = Mode::Editing;
+                self.frames_since_error = None;
+            }
+            KeyCode::Char(':') => {
+                self.mode = Mode::Command;
+                self.frames_since_error = None;
+                self.history_pos = None;
+                self.command_field.clear();
+            }
+            _ => {}
+        }
+    }
+
+    // goes into the field being typed in as one edit, the fields hold a single line
+    fn paste(&mut self, text: &str) {
+        let text = text.lines().join(" ");
+        let field = match self.mode {
+            _ if self.dialogue != Dialogue::None => return,
+            Mode::Command => &mut self.command_field,
+            Mode::Input => &mut self.input_field,
+            Mode::Editing => &mut self.edit_field,
+            Mode::Normal => return,
+        };
+        field.insert_str(&text);
+    }
+
+    fn dispatch_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        if self.dialogue == Dialogue::Message {
+            self.message_popup.handle_input(key)
+        } else if matches!(self.dialogue, Dialogue::Quit | Dialogue::Reset) {
+            self.confirm_popup.handle_input(key)
+        } else if self.dialogue == Dialogue::Save {
+            self.save_popup.handle_input(key)
+        } else if self.dialogue == Dialogue::Examples {
+            self.examples_popup.handle_input(key)
+        } else if self.mode == Mode::Command {
             match key {
+                KeyCode::Char('z') if ctrl => self.command_field.undo(),
+                KeyCode::Char('y') if ctrl => self.command_field.redo(),
                 KeyCode::Char(c) => self.command_field.insert(c),
                 KeyCode::Backspace => self.command_field.remove(),
+                KeyCode::Delete => self.command_field.delete_forward(),
                 KeyCode::Enter => {
                     self.mode = Mode::Normal;
+                    self.push_history();
                     self.process_command();
                     self.command_field.move_cursor_home();
                 }
@@ -265,8 +758,54 @@ impl App {
                     self.mode = Mode::Normal;
                     self.command_field.move_cursor_home();
                 }
+                KeyCode::Left if ctrl => self.command_field.move_cursor_word_left(),
+                KeyCode::Right if ctrl => self.command_field.move_cursor_word_right(),
                 KeyCode::Left => self.command_field.move_cursor_left(),
                 KeyCode::Right => self.command_field.move_cursor_right(),
+                KeyCode::Home => self.command_field.move_cursor_home(),
+                KeyCode::End => self.command_field.move_cursor_end(),
+                KeyCode::Up => self.history_back(),
+                KeyCode::Down => self.history_forward(),
+                KeyCode::Tab => self.complete_option(),
+                _ => {}
+            }
+            true
+        } else if self.mode == Mode::Input {
+            match key {
+                KeyCode::Char('z') if ctrl => self.input_field.undo(),
+                KeyCode::Char('y') if ctrl => self.input_field.redo(),
+                KeyCode::Char(c) => self.input_field.insert(c),
+                KeyCode::Backspace => self.input_field.remove(),
+                KeyCode::Delete => self.input_field.delete_forward(),
+                KeyCode::Enter => self.submit_input(),
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    self.input_field.move_cursor_home();
+                }
+                KeyCode::Left if ctrl => self.input_field.move_cursor_word_left(),
+                KeyCode::Right if ctrl => self.input_field.move_cursor_word_right(),
+                KeyCode::Left => self.input_field.move_cursor_left(),
+                KeyCode::Right => self.input_field.move_cursor_right(),
+                KeyCode::Home => self.input_field.move_cursor_home(),
+                KeyCode::End => self.input_field.move_cursor_end(),
+                _ => {}
+            }
+            true
+        } else if self.mode == Mode::Editing {
+            match key {
+                KeyCode::Char('z') if ctrl => self.edit_field.undo(),
+                KeyCode::Char('y') if ctrl => self.edit_field.redo(),
+                KeyCode::Char(c) => self.edit_field.insert(c),
+                KeyCode::Backspace => self.edit_field.remove(),
+                KeyCode::Delete => self.edit_field.delete_forward(),
+                KeyCode::Enter => self.submit_code(),
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Left if ctrl => self.edit_field.move_cursor_word_left(),
+                KeyCode::Right if ctrl => self.edit_field.move_cursor_word_right(),
+                KeyCode::Left => self.edit_field.move_cursor_left(),
+                KeyCode::Right => self.edit_field.move_cursor_right(),
+                KeyCode::Home => self.edit_field.move_cursor_home(),
+                KeyCode::End => self.edit_field.move_cursor_end(),
                 _ => {}
             }
             true
@@ -275,88 +814,3211 @@ impl App {
         }
     }
 
-    // currently doesnt support arguments with spaces included
+    // queues the typed line and a newline. an empty line is the end of input, so
+    // nothing is queued and a waiting `,` is left to the eof policy
+    fn submit_input(&mut self) {
+        self.mode = Mode::Normal;
+        let line = self.input_field.take();
+        self.input_field.move_cursor_home();
+        if line.is_empty() {
+            if self.interp.state == InterpState::Waiting {
+                self.post_info("end of input, :set eof to choose what ',' reads".to_string());
+            }
+            return;
+        }
+        self.interp.push_input(line.as_bytes());
+        self.interp.push_input(b"\n");
+        self.lines.push(ReplLine::new(ReplType::Input, line));
+    }
+
+    // appends the edited line to the program, staying in Editing for the next one.
+    // a line with unbalanced brackets is kept in the field to fix after pressing e,
+    // and Editing is left so the bottom bar can show the error
+    fn submit_code(&mut self) {
+        let line = self.edit_field.get_str().to_string();
+        if line.is_empty() {
+            return;
+        }
+        match self.interp.extend_prog_partial(line.as_bytes()) {
+            Ok(true) => {}
+            Ok(false) => self.post_info("loop still open, waiting for ']'".to_string()),
+            Err(e) => {
+                self.mode = Mode::Normal;
+                self.post_error(e.to_string());
+                return;
+            }
+        }
+        self.edit_field.clear();
+        self.lines.push(ReplLine::new(ReplType::Code, line));
+    }
+
+    // arguments are split on spaces, paths with spaces need double quotes
     fn process_command(&mut self) {
-        let mut parsed_command = self.command_field.get_str().split(' ');
-        match parsed_command.next().unwrap() {
+        let command = self.command_field.get_str().to_string();
+        let (name, args) = command.split_once(' ').unwrap_or((command.as_str(), ""));
+        match name {
+            "help" => self.show_help(),
             "quit" | "q" => self.try_quit(),
             "quit!" | "q!" => self.force_quit(),
+            "input" => {
+                self.interp.push_input(args.as_bytes());
+                self.lines
+                    .push(ReplLine::new(ReplType::Input, args.to_string()));
+            }
+            "feedcell" => {
+                // wider cells are queued as their low byte, like `.` writes them
+                let cell = self.interp.mem[self.interp.mem_ptr] as u8;
+                self.interp.push_input(&[cell]);
+                self.post_info(format!("queued {cell} as input"));
+            }
+            "eval" => self.eval(args),
+            "clear-repl" => {
+                self.end_partial_output();
+                self.lines.clear();
+                self.output_lines.clear();
+                self.repl_scroll = 0;
+            }
+            "runin" | "run" => self.run_until_input(),
+            "run!" => {
+                self.interp.ignore_breakpoints = true;
+                self.run_until_input();
+                self.interp.ignore_breakpoints = false;
+            }
+            "break" | "unbreak" => match args.parse::<usize>() {
+                Ok(offset) if name == "break" => {
+                    self.interp.breakpoints.insert(offset);
+                }
+                Ok(offset) => {
+                    self.interp.breakpoints.remove(&offset);
+                }
+                Err(_) => {
+                    self.post_error(format!("{name}: expected a program offset, got '{args}'"))
+                }
+            },
+            "cbreak" => match args {
+                "clear" => self.interp.conditions.clear(),
+                _ => match CellCondition::parse(args) {
+                    Ok(mut condition) => {
+                        // only stop when it becomes true, not because it already is
+                        condition.update(&self.interp.mem);
+                        self.interp.conditions.push(condition);
+                    }
+                    Err(e) => self.post_error(format!("cbreak: {e}")),
+                },
+            },
+            "watch" | "unwatch" => match args.parse::<usize>() {
+                Ok(cell) if name == "watch" => {
+                    self.interp.watchpoints.insert(cell);
+                }
+                Ok(cell) => {
+                    self.interp.watchpoints.remove(&cell);
+                }
+                Err(_) => self.post_error(format!("{name}: expected a cell index, got '{args}'")),
+            },
+            "continue" | "c" => self.continue_run(),
+            "reset" if self.interp.step_count > 0 => {
+                self.confirm_popup.title = " Reset ".to_string();
+                self.confirm_popup.body = format!(
+                    "Reset after {} steps and clear the REPL?",
+                    self.interp.step_count
+                );
+                self.confirm_popup.show();
+                self.dialogue = Dialogue::Reset;
+            }
+            "reset" => self.reset_session(),
+            "state" => self.state_command(args),
+            "loopback" => match self.interp.rewind_to_iteration_start() {
+                Some(steps) => self.post_info(format!(
+                    "rewound {steps} steps to the start of the iteration"
+                )),
+                None if self.interp.current_depth() == 0 => {
+                    self.post_error("loopback: the pc is not inside a loop".to_string())
+                }
+                None => self.post_error(
+                    "loopback: the undo log doesn't reach the iteration start".to_string(),
+                ),
+            },
+            "poke" => self.poke_command(args),
+            "name" => {
+                if let Some(args) = self.command_args(name, args) {
+                    self.session_name = (!args.is_empty()).then(|| args.join(" "));
+                }
+            }
+            "replaysteps" => match args.parse::<usize>() {
+                Ok(n) => self.replay_steps(n),
+                Err(_) => {
+                    self.post_error(format!("replaysteps: expected a step count, got '{args}'"))
+                }
+            },
+            "dryrun" => match args.parse::<u64>() {
+                Ok(n) => self.dry_run(n),
+                Err(_) => self.post_error(format!("dryrun: expected a step count, got '{args}'")),
+            },
+            "step" => match args.parse::<u64>() {
+                Ok(n) => self.step_n(n),
+                Err(_) => self.post_error(format!("step: expected a step count, got '{args}'")),
+            },
+            "speed" => match args.parse::<u64>() {
+                Ok(0) => {
+                    self.auto_run = false;
+                    self.post_info("paused".to_string());
+                }
+                Ok(n) => {
+                    self.options.steps_per_frame = n;
+                    self.post_info(format!("running {n} steps per frame"));
+                }
+                Err(_) => self.post_error(format!("speed: expected a step count, got '{args}'")),
+            },
+            "nextout" => self.run_to_next_output(),
+            "tape" => self.tape_command(args),
+            "snap" => self.snap_command(args),
+            "expect" => self.expect_command(args),
+            "trace" => self.trace_command(args),
+            "record" => self.record_command(args),
+            "layout" => match args {
+                "split" => self.set_layout(PanelLayout::Split),
+                "noinfo" => self.set_layout(PanelLayout::NoInfo),
+                "memory" => self.set_layout(PanelLayout::MemoryOnly),
+                _ => self.post_error(format!(
+                    "layout: expected split, noinfo or memory, got '{args}'"
+                )),
+            },
+            "memview" => match args {
+                "dec" => self.options.memory_view = MemoryView::Decimal,
+                "hex" => self.options.memory_view = MemoryView::Hex,
+                "bin" => self.options.memory_view = MemoryView::Binary,
+                "glyph" => self.options.memory_view = MemoryView::Glyph,
+                "ascii" => self.options.memory_view = MemoryView::Ascii,
+                _ => self.post_error(format!(
+                    "memview: expected dec, hex, bin, glyph or ascii, got '{args}'"
+                )),
+            },
+            "set" => self.set_option(args),
+            "progdiff" => {
+                if let Some(path) = self.path_arg(name, args) {
+                    self.show_prog_diff(&path);
+                }
+            }
+            "hot" => self.show_hotspots(args),
+            "hash" => self.post_info(format!(
+                "instruction hash: {:016x}",
+                self.interp.instruction_hash()
+            )),
+            "format" => self.format_program(args),
+            "match" => self.match_bracket(args),
+            "loopinfo" => self.show_loop_info(args),
+            "extent" => match self.interp.static_extent() {
+                Some((min, max)) if min < 0 => self.post_error(format!(
+                    "program reaches cells {min}..={max}, it moves left of the starting cell"
+                )),
+                Some((min, max)) => self.post_info(format!("program reaches cells {min}..={max}")),
+                None => self.post_info("tape extent depends on the data".to_string()),
+            },
+            "load" => {
+                if let Some(path) = self.path_arg(name, args) {
+                    self.load_file(&path);
+                }
+            }
+            "save" if args.is_empty() => {
+                self.save_popup = TextEntryPopup::with_default(
+                    " Save as ".to_string(),
+                    1,
+                    "program.bf".to_string(),
+                );
+                self.save_popup.show();
+                self.dialogue = Dialogue::Save;
+            }
+            "save" => {
+                if let Some(path) = self.path_arg(name, args) {
+                    self.save_file(&path);
+                }
+            }
+            "examples" => self.examples_command(args),
+            "outmap" => self.outmap_command(args),
+            "inputview" => self.show_input_view(),
+            "ranges" => self.ranges_command(args),
+            "reachable" => self.post_info(format!(
+                "{} of {} instructions reachable from offset {}",
+                self.interp.reachable_from(self.interp.prog_ptr),
+                self.interp.instruction_count(),
+                self.interp.prog_ptr
+            )),
+            "outsize" => match self.interp.static_output_count() {
+                (count, true) => self.post_info(format!("program outputs {count} bytes")),
+                (count, false) => self.post_info(format!(
+                    "program outputs at least {count} bytes, loops make the rest indeterminate"
+                )),
+            },
+            "annotate" => {
+                let Some(args) = self.command_args(name, args) else {
+                    return;
+                };
+                let (offset, note) = match args.split_first() {
+                    Some((offset, note)) => (offset.as_str(), note.join(" ")),
+                    None => ("", String::new()),
+                };
+                match offset.parse::<usize>() {
+                    Ok(offset) => match self.interp.annotate(offset, &note) {
+                        0 => {
+                            self.post_error("annotate: note has no comment chars left".to_string())
+                        }
+                        len => self.post_info(format!("inserted {len} bytes at offset {offset}")),
+                    },
+                    Err(_) => self.post_error(format!(
+                        "annotate: expected a program offset, got '{offset}'"
+                    )),
+                }
+            }
+            "freeze" | "unfreeze" => match args.parse::<usize>() {
+                Ok(cell) if name == "freeze" => {
+                    self.interp.frozen.insert(cell);
+                }
+                Ok(cell) => {
+                    self.interp.frozen.remove(&cell);
+                }
+                Err(_) => self.post_error(format!("{name}: expected a cell index, got '{args}'")),
+            },
             _ => self.post_error(format!("Unknown Command: {}", self.command_field.get_str())),
         }
     }
 
-    fn post_error(&mut self, err_str: String) {
-        self.frames_since_error = Some(0);
-        self.error_str = err_str;
+    // tokenized arguments, posting the parse error if there are none
+    fn command_args(&mut self, name: &str, args: &str) -> Option<Vec<String>> {
+        match split_args(args) {
+            Ok(args) => Some(args),
+            Err(e) => {
+                self.post_error(format!("{name}: {e}"));
+                None
+            }
+        }
     }
 
-    fn force_quit(&mut self) {
-        self.running_mode = RunningMode::Exiting;
+    // the single path argument of a command, empty if it was left out
+    fn path_arg(&mut self, name: &str, args: &str) -> Option<String> {
+        let mut args = self.command_args(name, args)?;
+        match args.len() {
+            0 => Some(String::new()),
+            1 => args.pop(),
+            _ => {
+                self.post_error(format!(
+                    "{name}: expected one path, quote it if it contains spaces"
+                ));
+                None
+            }
+        }
     }
 
-    fn try_quit(&mut self) {
-        self.force_quit();
+    fn show_help(&mut self) {
+        let width = COMMANDS
+            .iter()
+            .map(|(usage, _)| usage.len())
+            .max()
+            .unwrap_or(0);
+        let commands = COMMANDS
+            .iter()
+            .map(|(usage, desc)| format!(":{usage:<width$}  {desc}"))
+            .join("\n");
+        let keys = KEY_BINDINGS
+            .iter()
+            .map(|(key, desc)| format!("{key:>10}  {desc}"))
+            .join("\n");
+        self.show_message(" Help ", format!("{keys}\n\n{commands}"));
     }
 
-    fn render_title_bar(&self, area: Rect, buf: &mut Buffer) {
-        let horizontal = Layout::horizontal([
-            Constraint::Min(0),
-            Constraint::Length(9),
-            Constraint::Length(10),
-        ]);
-        let [app_name, editing_mode_area, repl_mode_area] = horizontal.areas(area);
+    fn show_message(&mut self, title: &str, body: String) {
+        self.message_popup.show(title.to_string(), body);
+        self.dialogue = Dialogue::Message;
+    }
 
-        //Block::new().style(THEME.root).render(area, buf);
-        Paragraph::new("BFRepl").render(app_name, buf);
-        match self.mode {
-            Mode::Normal => Span::from(" Normal ").style(THEME.mode.normal),
-            Mode::Editing => Span::from(" Editing ").style(THEME.mode.editing),
-            Mode::Command => Span::from(" Command ").style(THEME.mode.command),
+    // outmap <byte> <text> | outmap del <byte> | outmap clear
+    fn outmap_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("outmap", args) else {
+            return;
+        };
+        let (first, rest) = match args.split_first() {
+            Some((first, rest)) => (first.as_str(), rest.join(" ")),
+            None => ("", String::new()),
+        };
+        match first {
+            "clear" => self.interp.output_table.clear(),
+            "del" => match rest.parse::<u8>() {
+                Ok(byte) => {
+                    self.interp.output_table.remove(&byte);
+                }
+                Err(_) => self.post_error(format!("outmap: expected a byte value, got '{rest}'")),
+            },
+            _ => match first.parse::<u8>() {
+                Ok(byte) if !rest.is_empty() => {
+                    self.interp.output_table.insert(byte, rest);
+                }
+                Ok(_) => self.post_error("usage: outmap <byte> <text>".to_string()),
+                Err(_) => self.post_error(format!("outmap: expected a byte value, got '{first}'")),
+            },
         }
-        .render(editing_mode_area, buf);
-        match self.interp.state {
-            InterpState::Running => Span::from(" Running  ").style(THEME.mode.editing),
-            InterpState::Finished => Span::from(" Finished ").style(THEME.mode.normal),
-            InterpState::Waiting => Span::from(" Waiting  ").style(THEME.mode.command),
+    }
+
+    // picks a bundled program from a list, or loads one by name or number
+    fn examples_command(&mut self, args: &str) {
+        if args.is_empty() {
+            let names = EXAMPLES
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| format!("{}  {name}", i + 1))
+                .collect();
+            self.examples_popup.show(" Examples ".to_string(), names);
+            self.dialogue = Dialogue::Examples;
+            return;
+        }
+
+        let example = EXAMPLES.iter().enumerate().position(|(i, (name, _))| {
+            *name == args || args.parse::<usize>().is_ok_and(|n| n == i + 1)
+        });
+        match example {
+            Some(i) => self.load_example(i),
+            None => self.post_error(format!("examples: no example called '{args}'")),
         }
-        .render(repl_mode_area, buf);
     }
 
-    /*
-        need to only render useable controls for currently selected tab.
-        so render common followed by specific controls.
-    */
-    fn render_bottom_bar(&self, area: Rect, buf: &mut Buffer) {
-        let common_keys: [(&'static str, &'static str); 2] = [("Q", "Quit"), ("n", "Next Tab")];
+    fn load_example(&mut self, index: usize) {
+        let Some((name, prog)) = EXAMPLES.get(index) else {
+            return;
+        };
+        self.load_program(prog);
+        self.post_info(format!("loaded example {name}"));
+    }
 
-        let spans: Vec<Span> = common_keys
-            .iter()
-            .flat_map(|(key, desc)| {
-                let key = Span::from(format!(" {key} ")).style(THEME.key_bind);
-                let desc = Span::from(format!(" {desc} ")).style(THEME.key_desc);
-                [key, desc]
-            })
-            .collect();
+    // replaces the program and clears the tape so it starts like a fresh run
+    fn load_program(&mut self, prog: &[u8]) {
+        if let Err(e) = self.interp.replace_prog(prog) {
+            self.post_error(e.to_string());
+            return;
+        }
 
-        Line::from(spans).centered().render(area, buf);
+        self.program_mark = None;
+        self.replay_remaining = 0;
+        self.auto_run = false;
+        self.interp.reset();
+        self.end_partial_output();
+        self.lines.push(ReplLine::new(
+            ReplType::Code,
+            String::from_utf8_lossy(prog).split_whitespace().join(" "),
+        ));
     }
-}
 
-fn main() -> io::Result<()> {
-    let mut terminal = tui::init()?;
-    let mut app = App {
-        mode: Mode::Normal,
-        running_mode: RunningMode::Running,
-        options: Options {
-            error_display_time: 2,
-            refresh_rate: 60,
-        },
-        lines: vec![ReplType::Code, ReplType::Code, ReplType::Output],
-        interp: BFInt::new(),
-        command_field: TextEntry::default(),
-        error_str: String::new(),
-        frames_since_error: None,
-    };
-    app.run(&mut terminal)?;
-    tui::restore()
+    // ranges on | ranges off | ranges
+    fn ranges_command(&mut self, args: &str) {
+        match (args, &self.interp.cell_ranges) {
+            ("on", _) => self.interp.enable_ranges(),
+            ("off", _) => self.interp.cell_ranges = None,
+            ("", None) => self.post_error("ranges: tracking is off, run :ranges on".to_string()),
+            ("", Some(ranges)) => {
+                let rows: Vec<String> = ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (min, max))| min != max)
+                    .map(|(cell, (min, max))| format!("{cell:>5} {min:>5} {max:>5}"))
+                    .collect();
+                if rows.is_empty() {
+                    self.post_info("no cell has changed value yet".to_string());
+                } else {
+                    let body = format!(" cell   min   max\n{}", rows.join("\n"));
+                    self.show_message(" Cell ranges ", body);
+                }
+            }
+            _ => self.post_error(format!("ranges: expected on or off, got '{args}'")),
+        }
+    }
+
+    fn show_input_view(&mut self) {
+        if self.interp.input.is_empty() {
+            self.post_info(format!(
+                "input queue is empty, {} bytes read so far",
+                self.interp.input_read
+            ));
+            return;
+        }
+
+        let queued: Vec<u8> = self.interp.input.iter().copied().collect();
+        let mut rows = hex_dump(&queued, self.interp.input_read);
+        // the next byte `,` will read is always the front of the queue
+        rows.insert(1, format!("{}^^ next", " ".repeat(8)));
+        self.show_message(" Input queue ", rows.join("\n"));
+    }
+
+    fn show_loop_info(&mut self, args: &str) {
+        let Ok(offset) = args.parse::<usize>() else {
+            self.post_error(format!("loopinfo: expected a program offset, got '{args}'"));
+            return;
+        };
+        let Some(effects) = self.interp.loop_effects(offset) else {
+            self.post_error(format!("loopinfo: no loop starts at offset {offset}"));
+            return;
+        };
+
+        let body = format!(
+            "net pointer movement: {:+}\nreads cells:  {}\nwrites cells: {}",
+            effects.net_move,
+            effects.reads.iter().map(|c| format!("{c:+}")).join(" "),
+            effects.writes.iter().map(|c| format!("{c:+}")).join(" "),
+        );
+        self.show_message(&format!(" Loop at {offset} "), body);
+    }
+
+    fn match_bracket(&mut self, args: &str) {
+        let Ok(offset) = args.parse::<usize>() else {
+            self.post_error(format!("match: expected a program offset, got '{args}'"));
+            return;
+        };
+
+        match self.interp.matching_bracket(offset) {
+            Some(partner) => {
+                self.program_mark = Some(partner);
+                self.post_info(format!(
+                    "{} at {offset} matches {} at {partner}",
+                    self.interp.prog[offset] as char, self.interp.prog[partner] as char
+                ));
+            }
+            None => self.post_error(format!("match: no bracket at offset {offset}")),
+        }
+    }
+
+    fn format_program(&mut self, args: &str) {
+        let formatted = if self.options.format_keep_comments {
+            format_source(&self.interp.prog, "  ", true)
+        } else {
+            self.interp.format_indented("  ")
+        };
+
+        match args {
+            "" => self.show_message(" Formatted program ", formatted),
+            "write" if self.interp.prog_ptr == 0 => {
+                match self.interp.relayout_prog(formatted.as_bytes()) {
+                    Ok(()) => {
+                        self.program_mark = None;
+                        self.post_info("program reformatted".to_string());
+                    }
+                    Err(e) => self.post_error(format!("format write: {e}")),
+                }
+            }
+            "write" => self.post_error("format write: reset the program first".to_string()),
+            _ => self.post_error(format!("Unknown format argument: {args}")),
+        }
+    }
+
+    fn show_hotspots(&mut self, args: &str) {
+        let n = match args {
+            "" => 10,
+            _ => match args.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    self.post_error(format!("hot: expected a count, got '{args}'"));
+                    return;
+                }
+            },
+        };
+
+        let hot = self.interp.hotspots(n);
+        if hot.is_empty() {
+            self.post_info("no instructions have been executed".to_string());
+            return;
+        }
+
+        let body = hot
+            .iter()
+            .map(|&(pc, count)| format!("{pc:>6}  {}  {count:>10}", self.interp.prog[pc] as char))
+            .join("\n");
+        self.show_message(" Hottest instructions ", body);
+    }
+
+    // appends a program file, whitespace and newlines in it are just ignored bytes
+    fn load_file(&mut self, path: &str) {
+        if path.is_empty() {
+            self.post_error("load: missing path argument".to_string());
+            return;
+        }
+        let prog = match std::fs::read(path) {
+            Ok(prog) => prog,
+            Err(e) => {
+                self.post_error(format!("{path}: {e}"));
+                return;
+            }
+        };
+
+        if let Err(e) = self.interp.extend_prog(&prog) {
+            self.post_error(format!("{path}: {e}"));
+            return;
+        }
+        self.lines.push(ReplLine::new(
+            ReplType::Code,
+            String::from_utf8_lossy(&prog).split_whitespace().join(" "),
+        ));
+    }
+
+    fn save_file(&mut self, path: &str) {
+        if path.is_empty() {
+            self.post_error("save: missing path".to_string());
+            return;
+        }
+        match std::fs::write(path, &self.interp.prog) {
+            Ok(()) => self.post_info(format!(
+                "{} bytes written to {path}",
+                self.interp.prog.len()
+            )),
+            Err(e) => self.post_error(format!("{path}: {e}")),
+        }
+    }
+
+    fn show_prog_diff(&mut self, path: &str) {
+        if path.is_empty() {
+            self.post_error("progdiff: missing path".to_string());
+            return;
+        }
+        let other = match std::fs::read(path) {
+            Ok(other) => other,
+            Err(e) => {
+                self.post_error(format!("{path}: {e}"));
+                return;
+            }
+        };
+
+        let edits = instruction_diff(&self.interp.prog, &other);
+        if edits.is_empty() {
+            self.post_info(format!("no instruction differences with {path}"));
+            return;
+        }
+
+        let body = edits
+            .iter()
+            .map(|edit| match *edit {
+                DiffEdit::Removed { offset, op } => format!("- {offset:>6}  {}", op as char),
+                DiffEdit::Added { offset, op } => format!("+ {offset:>6}  {}", op as char),
+            })
+            .join("\n");
+        self.show_message(&format!(" Diff against {path} "), body);
+    }
+
+    // remembers the entered command unless it repeats the previous one
+    fn push_history(&mut self) {
+        let command = self.command_field.get_str();
+        if !command.is_empty() && self.command_history.last().map(String::as_str) != Some(command) {
+            self.command_history.push(command.to_string());
+        }
+    }
+
+    // Up shows older commands, stopping at the oldest
+    fn history_back(&mut self) {
+        let pos = match self.history_pos {
+            _ if self.command_history.is_empty() => return,
+            None => self.command_history.len() - 1,
+            Some(pos) => pos.saturating_sub(1),
+        };
+        self.history_pos = Some(pos);
+        self.command_field
+            .set_text(self.command_history[pos].clone());
+        self.command_field.move_cursor_end();
+    }
+
+    // Down shows newer commands, then an empty field past the newest
+    fn history_forward(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.command_history.len() {
+            self.history_pos = Some(pos + 1);
+            self.command_field
+                .set_text(self.command_history[pos + 1].clone());
+            self.command_field.move_cursor_end();
+        } else {
+            self.history_pos = None;
+            self.command_field.clear();
+        }
+    }
+
+    // fills in the option name after `set` when only one option matches what was typed
+    fn complete_option(&mut self) {
+        let Some(prefix) = self.command_field.get_str().strip_prefix("set ") else {
+            return;
+        };
+        if prefix.contains(' ') {
+            return;
+        }
+
+        let matches: Vec<&str> = SET_OPTIONS
+            .iter()
+            .copied()
+            .filter(|o| o.starts_with(prefix))
+            .collect();
+        match matches.as_slice() {
+            [option] => {
+                self.command_field.set_text(format!("set {option} "));
+                self.command_field.move_cursor_end();
+            }
+            [] => {}
+            _ => self.post_info(matches.join(" ")),
+        }
+    }
+
+    fn set_option(&mut self, args: &str) {
+        let (option, value) = args.split_once(' ').unwrap_or((args, ""));
+        match self.apply_option(option, value) {
+            Ok(()) => self.post_info(format!("{option} set to {value}")),
+            Err(e) => self.post_error(e),
+        }
+    }
+
+    fn apply_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "eof" => match value {
+                "wait" => self.interp.set_eof_behavior(EofBehavior::Wait),
+                "keep" => self.interp.set_eof_behavior(EofBehavior::Unchanged),
+                "zero" => self.interp.set_eof_behavior(EofBehavior::Zero),
+                "max" => self.interp.set_eof_behavior(EofBehavior::Max),
+                _ => {
+                    return Err(format!(
+                        "eof: expected wait, keep, zero or max, got '{value}'"
+                    ))
+                }
+            },
+            "arith" => match value {
+                "wrap" => self.interp.arithmetic = ArithmeticMode::Wrapping,
+                "sat" => self.interp.arithmetic = ArithmeticMode::Saturating,
+                _ => return Err(format!("arith: expected wrap or sat, got '{value}'")),
+            },
+            "cellwidth" => match value {
+                "8" => self.interp.set_cell_width(CellWidth::U8),
+                "16" => self.interp.set_cell_width(CellWidth::U16),
+                "32" => self.interp.set_cell_width(CellWidth::U32),
+                _ => return Err(format!("cellwidth: expected 8, 16 or 32, got '{value}'")),
+            },
+            "output" => match value {
+                "on" => self.interp.set_output_enabled(true),
+                "off" => self.interp.set_output_enabled(false),
+                _ => return Err(format!("output: expected on or off, got '{value}'")),
+            },
+            "frozen" => match value {
+                "ignore" => self.interp.frozen_mode = FrozenMode::Ignore,
+                "pause" => self.interp.frozen_mode = FrozenMode::Pause,
+                _ => return Err(format!("frozen: expected ignore or pause, got '{value}'")),
+            },
+            "comments" => match value {
+                "keep" => self.options.format_keep_comments = true,
+                "strip" => self.options.format_keep_comments = false,
+                _ => return Err(format!("comments: expected keep or strip, got '{value}'")),
+            },
+            "marker" => match value {
+                "on" => self.options.pointer_marker = true,
+                "off" => self.options.pointer_marker = false,
+                _ => return Err(format!("marker: expected on or off, got '{value}'")),
+            },
+            "visitlimit" => match value {
+                "off" => self.interp.visit_limit = None,
+                _ => match value.parse::<u64>() {
+                    Ok(max) if max > 0 => self.interp.visit_limit = Some(max),
+                    _ => {
+                        return Err(format!(
+                            "visitlimit: expected a visit count or off, got '{value}'"
+                        ))
+                    }
+                },
+            },
+            "tapelimit" => match value {
+                "off" => self.interp.tape_limit = None,
+                _ => match value.parse::<usize>() {
+                    Ok(max) if max > 0 => self.interp.tape_limit = Some(max),
+                    _ => {
+                        return Err(format!(
+                            "tapelimit: expected a cell count or off, got '{value}'"
+                        ))
+                    }
+                },
+            },
+            "dim" => match value {
+                "on" => self.options.dim_executed = true,
+                "off" => self.options.dim_executed = false,
+                _ => return Err(format!("dim: expected on or off, got '{value}'")),
+            },
+            "confirmquit" => match value {
+                "on" => self.options.confirm_quit_key = true,
+                "off" => self.options.confirm_quit_key = false,
+                _ => return Err(format!("confirmquit: expected on or off, got '{value}'")),
+            },
+            "outpanel" => {
+                match value {
+                    "on" => self.options.output_panel = true,
+                    "off" => self.options.output_panel = false,
+                    _ => return Err(format!("outpanel: expected on or off, got '{value}'")),
+                }
+                self.end_partial_output();
+            }
+            "cr" => match value {
+                "on" => self.options.carriage_return = true,
+                "off" => self.options.carriage_return = false,
+                _ => return Err(format!("cr: expected on or off, got '{value}'")),
+            },
+            "errors" => match value {
+                "popup" => self.options.error_popup = true,
+                "status" => self.options.error_popup = false,
+                _ => return Err(format!("errors: expected popup or status, got '{value}'")),
+            },
+            "resetkey" => match parse_key(value) {
+                // the reset key is checked first, it would hide the default binding
+                Some((KeyCode::Char(c), KeyModifiers::NONE))
+                    if KEY_BINDINGS.iter().any(|(key, _)| *key == c.to_string()) =>
+                {
+                    return Err(format!("resetkey: '{value}' is already bound"));
+                }
+                Some(key) => self.options.reset_key = key,
+                None => {
+                    return Err(format!(
+                        "resetkey: expected a key like r or ctrl-r, got '{value}'"
+                    ))
+                }
+            },
+            "tabwidth" => match value.parse::<usize>() {
+                Ok(width) if width > 0 => self.options.tab_width = width,
+                _ => {
+                    return Err(format!(
+                        "tabwidth: expected a positive width, got '{value}'"
+                    ))
+                }
+            },
+            "ptrlimit" => match value {
+                "off" => self.interp.pointer_limit = None,
+                _ => match value.parse::<usize>() {
+                    Ok(max) => self.interp.pointer_limit = Some(max),
+                    Err(_) => {
+                        return Err(format!(
+                            "ptrlimit: expected a cell index or off, got '{value}'"
+                        ))
+                    }
+                },
+            },
+            _ => return Err(format!("Unknown option: {option}")),
+        }
+        Ok(())
+    }
+
+    // recordings reuse the trace buffer with no capacity limit
+    fn record_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("record", args) else {
+            return;
+        };
+        let mut args = args.iter().map(String::as_str);
+        match (args.next(), &self.recording) {
+            (Some("start"), Some(path)) => {
+                self.post_error(format!("record: already recording to {path}"))
+            }
+            (Some("start"), None) if self.interp.trace.is_some() => {
+                self.post_error("record: the trace is in use, run :trace off first".to_string())
+            }
+            (Some("start"), None) => match args.next() {
+                Some(path) => {
+                    self.interp.enable_trace(usize::MAX);
+                    self.recording = Some(path.to_string());
+                }
+                None => self.post_error("record start: missing path".to_string()),
+            },
+            (Some("stop"), Some(_)) => {
+                let path = self.recording.take().unwrap();
+                let frames = self.interp.trace_frames();
+                self.interp.trace = None;
+
+                let mut contents = frames.join("\n");
+                contents.push('\n');
+                match std::fs::write(&path, contents) {
+                    Ok(()) => self.post_info(format!("{} frames written to {path}", frames.len())),
+                    Err(e) => self.post_error(format!("{path}: {e}")),
+                }
+            }
+            (Some("stop"), None) => self.post_error("record: not recording".to_string()),
+            (Some(sub), _) => self.post_error(format!("Unknown record command: {sub}")),
+            (None, _) => self.post_error("record: missing subcommand".to_string()),
+        }
+    }
+
+    fn trace_command(&mut self, args: &str) {
+        if let Some(path) = &self.recording {
+            self.post_error(format!("trace: in use while recording to {path}"));
+            return;
+        }
+
+        let Some(args) = self.command_args("trace", args) else {
+            return;
+        };
+        let mut args = args.iter().map(String::as_str);
+        match args.next() {
+            Some("on") => match args.next().map(str::parse::<usize>) {
+                None => self.interp.enable_trace(self.options.trace_capacity),
+                Some(Ok(capacity)) if capacity > 0 => self.interp.enable_trace(capacity),
+                Some(_) => {
+                    self.post_error("trace on: capacity must be a positive number".to_string())
+                }
+            },
+            Some("off") => self.interp.trace = None,
+            Some("export") => {
+                let Some(path) = args.next() else {
+                    self.post_error("trace export: missing path".to_string());
+                    return;
+                };
+                let Some(csv) = self.interp.trace_csv() else {
+                    self.post_error("trace export: no trace recorded, use :trace on".to_string());
+                    return;
+                };
+                match std::fs::write(path, csv) {
+                    Ok(()) => self.post_info(format!("trace written to {path}")),
+                    Err(e) => self.post_error(format!("{path}: {e}")),
+                }
+            }
+            Some(sub) => self.post_error(format!("Unknown trace command: {sub}")),
+            None => self.post_error("trace: missing subcommand".to_string()),
+        }
+    }
+
+    fn snap_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("snap", args) else {
+            return;
+        };
+        let mut args = args.iter().map(String::as_str);
+        match (args.next(), args.next()) {
+            (Some("save"), Some(name)) => {
+                self.snapshots
+                    .insert(name.to_string(), self.interp.snapshot());
+                self.post_info(format!("saved snapshot {name}"));
+            }
+            (Some("restore"), Some(name)) => match self.snapshots.get(name) {
+                Some(snapshot) if self.interp.restore(snapshot) => {
+                    self.post_info(format!("restored snapshot {name}"));
+                }
+                Some(_) => {
+                    self.post_error(format!("snap: {name} was taken on a different program"))
+                }
+                None => self.post_error(format!("snap: no snapshot named {name}")),
+            },
+            (Some("del"), Some(name)) => {
+                if self.snapshots.remove(name).is_none() {
+                    self.post_error(format!("snap: no snapshot named {name}"));
+                }
+            }
+            (Some("list"), None) if self.snapshots.is_empty() => {
+                self.post_info("no snapshots saved".to_string())
+            }
+            (Some("list"), None) => {
+                let body = self.snapshots.keys().sorted().join("\n");
+                self.show_message(" Snapshots ", body);
+            }
+            (Some(sub @ ("save" | "restore" | "del")), None) => {
+                self.post_error(format!("snap {sub}: missing name"))
+            }
+            (Some(sub), _) => self.post_error(format!("Unknown snap command: {sub}")),
+            (None, _) => self.post_error("snap: missing subcommand".to_string()),
+        }
+    }
+
+    fn expect_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("expect", args) else {
+            return;
+        };
+        let path = match args.as_slice() {
+            [sub, path] if sub == "tape" => path.clone(),
+            [sub] if sub == "tape" => {
+                self.post_error("expect tape: missing path".to_string());
+                return;
+            }
+            [sub, ..] if sub == "tape" => {
+                self.post_error(
+                    "expect tape: expected one path, quote it if it contains spaces".to_string(),
+                );
+                return;
+            }
+            [sub, ..] => {
+                self.post_error(format!("Unknown expect command: {sub}"));
+                return;
+            }
+            [] => {
+                self.post_error("expect: missing subcommand".to_string());
+                return;
+            }
+        };
+        let expected = match std::fs::read(&path) {
+            Ok(expected) => expected,
+            Err(e) => {
+                self.post_error(format!("{path}: {e}"));
+                return;
+            }
+        };
+
+        let expected: Vec<u32> = expected.into_iter().map(u32::from).collect();
+        let mismatches = self.interp.tape_mismatches(&expected);
+        if mismatches.is_empty() {
+            self.post_info(format!("tape matches {path} over {} cells", expected.len()));
+            return;
+        }
+
+        let body = mismatches
+            .iter()
+            .map(|(cell, want, have)| {
+                format!("cell {cell:>6}: expected {want:>3}, found {have:>3}")
+            })
+            .join("\n");
+        self.show_message(
+            &format!(" {} cells differ from {path} ", mismatches.len()),
+            body,
+        );
+    }
+
+    fn tape_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("tape", args) else {
+            return;
+        };
+        let mut args = args.iter().map(String::as_str);
+        match args.next() {
+            Some("load") => {
+                let Some(path) = args.next() else {
+                    self.post_error("tape load: missing path".to_string());
+                    return;
+                };
+                let mem_ptr = match args.next().map(str::parse::<usize>) {
+                    None => 0,
+                    Some(Ok(ptr)) => ptr,
+                    Some(Err(_)) => {
+                        self.post_error("tape load: pointer must be a cell index".to_string());
+                        return;
+                    }
+                };
+
+                match std::fs::read(path) {
+                    Ok(image) => {
+                        self.interp.load_tape(&image);
+                        self.interp.mem_ptr = mem_ptr.min(self.interp.mem.len() - 1);
+                    }
+                    Err(e) => self.post_error(format!("{path}: {e}")),
+                }
+            }
+            Some(sub) => self.post_error(format!("Unknown tape command: {sub}")),
+            None => self.post_error("tape: missing subcommand".to_string()),
+        }
+    }
+
+    fn run_until_input(&mut self) {
+        self.memory_pages = 0;
+        let outcome = self.interp.run_with_limit(self.options.run_step_limit);
+        self.flush_output();
+        self.report_outcome(outcome);
+    }
+
+    // poke <cell>=<value>,<cell>=<value> [run]
+    fn poke_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("poke", args) else {
+            return;
+        };
+        let mut args = args.into_iter();
+        let list = args.next().unwrap_or_default();
+        let then = args.join(" ");
+        if !matches!(then.as_str(), "" | "run") {
+            self.post_error(format!(
+                "poke: expected run after the assignments, got '{then}'"
+            ));
+            return;
+        }
+
+        let mut pokes = Vec::new();
+        for assignment in list.split(',').filter(|a| !a.is_empty()) {
+            let parsed = assignment.split_once('=').and_then(|(cell, value)| {
+                Some((cell.parse::<usize>().ok()?, value.parse::<u32>().ok()?))
+            });
+            match parsed {
+                Some(poke) => pokes.push(poke),
+                None => {
+                    self.post_error(format!("poke: expected <cell>=<value>, got '{assignment}'"));
+                    return;
+                }
+            }
+        }
+        if pokes.is_empty() {
+            self.post_error("usage: poke <cell>=<value>,... [run]".to_string());
+            return;
+        }
+
+        // check every assignment before applying any of them
+        let mem_len = self.interp.mem.len();
+        let limit = self.interp.tape_limit.unwrap_or(MAX_TAPE_LEN);
+        if let Some(&(cell, _)) = pokes
+            .iter()
+            .find(|&&(cell, _)| cell >= mem_len && cell >= limit)
+        {
+            self.post_error(format!("poke: cell {cell} is past the tape limit"));
+            return;
+        }
+        for (cell, value) in pokes {
+            self.interp.poke(cell, value);
+        }
+        if then == "run" {
+            self.run_until_input();
+        }
+    }
+
+    // state json [path] | state load <path>
+    fn state_command(&mut self, args: &str) {
+        let Some(args) = self.command_args("state", args) else {
+            return;
+        };
+        let mut args = args.iter().map(String::as_str);
+        match (args.next(), args.next()) {
+            (Some("json"), None) => {
+                self.show_message(" Interpreter state ", state::state_to_json(&self.interp))
+            }
+            (Some("json"), Some(path)) => {
+                match std::fs::write(path, state::state_to_json(&self.interp) + "\n") {
+                    Ok(()) => self.post_info(format!("state written to {path}")),
+                    Err(e) => self.post_error(format!("{path}: {e}")),
+                }
+            }
+            (Some("load"), Some(path)) => match std::fs::read_to_string(path) {
+                Ok(text) => match state::state_from_json(&text) {
+                    Ok(interp) => {
+                        let trace = self.interp.trace.take();
+                        let capacity = self.interp.trace_capacity;
+                        self.interp = interp;
+                        // a recording carries on from the loaded state
+                        if self.recording.is_some() {
+                            self.interp.trace = trace;
+                            self.interp.trace_capacity = capacity;
+                        }
+                        self.program_mark = None;
+                        self.post_info(format!("state loaded from {path}"));
+                    }
+                    Err(e) => self.post_error(format!("{path}: {e}")),
+                },
+                Err(e) => self.post_error(format!("{path}: {e}")),
+            },
+            _ => self.post_error("usage: state json [path] | state load <path>".to_string()),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.program_mark = None;
+        self.replay_remaining = 0;
+        self.auto_run = false;
+        self.interp.reset();
+        self.end_partial_output();
+        self.post_info("interpreter reset".to_string());
+    }
+
+    fn toggle_auto_run(&mut self) {
+        if self.auto_run {
+            self.auto_run = false;
+            self.post_info("paused".to_string());
+        } else if self.interp.state == InterpState::Finished {
+            self.post_info("program finished".to_string());
+        } else {
+            self.program_mark = None;
+            self.memory_pages = 0;
+            self.interp.begin_run();
+            self.auto_run = true;
+        }
+    }
+
+    // one frame of auto-running, pauses once the program stops for any reason
+    fn run_frame(&mut self) {
+        if !self.auto_run {
+            return;
+        }
+
+        let outcome = self
+            .interp
+            .resume_run(self.options.steps_per_frame as usize);
+        self.flush_output();
+        match outcome {
+            // resume_run only checks breakpoints after its first step
+            StepOutcome::Continue | StepOutcome::Output(_) if self.interp.at_breakpoint() => {
+                self.auto_run = false;
+                self.report_outcome(StepOutcome::Breakpoint(self.interp.prog_ptr));
+            }
+            StepOutcome::Continue | StepOutcome::Output(_) => {}
+            outcome => {
+                self.auto_run = false;
+                self.report_outcome(outcome);
+            }
+        }
+    }
+
+    // :reset also starts the transcript over
+    fn reset_session(&mut self) {
+        self.reset();
+        self.lines.clear();
+        self.output_lines.clear();
+        self.repl_scroll = 0;
+    }
+
+    // resumes a run that paused on ',' once input has been queued
+    fn continue_run(&mut self) {
+        match self.interp.state {
+            InterpState::Running => self.run_until_input(),
+            InterpState::Waiting => {
+                self.mode = Mode::Input;
+                self.input_field.clear();
+            }
+            InterpState::Finished => self.post_info("program finished".to_string()),
+        }
+    }
+
+    fn run_to_next_output(&mut self) {
+        self.memory_pages = 0;
+        for i in 0..self.options.run_step_limit {
+            if i > 0 && self.interp.at_breakpoint() {
+                self.flush_output();
+                self.report_outcome(StepOutcome::Breakpoint(self.interp.prog_ptr));
+                return;
+            }
+            match self.interp.step() {
+                StepOutcome::Continue => {}
+                StepOutcome::Output(_) => {
+                    self.flush_output();
+                    return;
+                }
+                outcome => {
+                    self.flush_output();
+                    self.report_outcome(outcome);
+                    return;
+                }
+            }
+        }
+        self.report_outcome(StepOutcome::Continue);
+    }
+
+    // tells the user why a bounded run stopped
+    fn report_outcome(&mut self, outcome: StepOutcome) {
+        match outcome {
+            StepOutcome::NeedsInput => {
+                self.mode = Mode::Input;
+                self.input_field.clear();
+            }
+            StepOutcome::Halted => self.post_info("program finished".to_string()),
+            StepOutcome::Output(_) => {}
+            StepOutcome::Continue => self.post_error(format!(
+                "stopped after {} steps",
+                self.options.run_step_limit
+            )),
+            StepOutcome::PointerLimit(cell) => self.post_error(format!(
+                "memory pointer moved past the limit to cell {cell}"
+            )),
+            StepOutcome::PointerUnderflow => self.post_error(format!(
+                "`<` at offset {} would move left of cell 0",
+                self.interp.prog_ptr
+            )),
+            StepOutcome::TapeLimit => self.post_error(format!(
+                "tape would grow past the limit of {} cells",
+                self.interp.tape_limit.unwrap_or_default()
+            )),
+            StepOutcome::Breakpoint(offset) => {
+                self.post_info(format!("stopped at breakpoint {offset}"))
+            }
+            StepOutcome::VisitLimit(pc) => {
+                self.program_mark = Some(pc);
+                self.post_error(format!(
+                    "offset {pc} ran more than {} times, possible infinite loop",
+                    self.interp.visit_limit.unwrap_or_default()
+                ));
+            }
+            StepOutcome::Condition(i) => self.post_info(format!(
+                "condition {} became true",
+                self.interp.conditions[i].text
+            )),
+            StepOutcome::Watchpoint(cell) => self.post_info(format!(
+                "watched cell {cell} changed to {} at offset {}",
+                self.interp.mem[cell],
+                self.interp.prog_ptr - 1
+            )),
+            StepOutcome::FrozenWrite(cell) => self.post_error(format!(
+                "write to frozen cell {cell} at offset {}",
+                self.interp.prog_ptr - 1
+            )),
+        }
+    }
+
+    // only keeps a page offset that still moves the view
+    fn page_memory(&mut self, pages: isize) {
+        let (per_row, visible_rows) = self.memory_grid.get();
+        let ptr_row = self.interp.mem_ptr / per_row;
+        let total_rows = self.interp.mem.len().div_ceil(per_row);
+        let first_row = |pages| memory_first_row(ptr_row, visible_rows, total_rows, pages);
+        if first_row(self.memory_pages + pages) != first_row(self.memory_pages) {
+            self.memory_pages += pages;
+        }
+    }
+
+    fn step_once(&mut self) {
+        self.program_mark = None;
+        self.memory_pages = 0;
+        if self.interp.instruction_count() == 0 {
+            self.post_info("no executable instructions in program".to_string());
+            return;
+        }
+
+        // a single step stops anyway, but the user should still hear why it was cut short
+        match self.interp.step() {
+            StepOutcome::Continue | StepOutcome::Output(_) | StepOutcome::Halted => {
+                self.flush_output()
+            }
+            outcome => {
+                self.flush_output();
+                self.report_outcome(outcome);
+            }
+        }
+    }
+
+    fn step_back_once(&mut self) {
+        self.program_mark = None;
+        self.memory_pages = 0;
+        if !self.interp.step_back() {
+            self.post_info("nothing to step back over".to_string());
+        }
+    }
+
+    fn step_n(&mut self, n: u64) {
+        self.program_mark = None;
+        self.memory_pages = 0;
+        let (steps, _, outcome) = self.interp.run_steps(n);
+        self.flush_output();
+        match outcome {
+            StepOutcome::Continue | StepOutcome::Output(_) => {
+                self.post_info(format!("ran {steps} steps"))
+            }
+            outcome => self.report_outcome(outcome),
+        }
+    }
+
+    // replay animation, one step every replay_delay_frames frames
+    fn replay_frame(&mut self) {
+        if self.replay_remaining == 0 {
+            return;
+        }
+        if self.replay_timer == 0 {
+            // the transcript already has this output from the first time round
+            let buffered = self.interp.output().len();
+            self.interp.step();
+            self.interp.truncate_output(buffered);
+            self.replay_remaining -= 1;
+            self.replay_timer = self.options.replay_delay_frames;
+        } else {
+            self.replay_timer -= 1;
+        }
+    }
+
+    // rewinds up to `n` steps, the main loop then steps forward again one per delay
+    fn replay_steps(&mut self, n: usize) {
+        let jumps = self.interp.recent_jumps(n);
+        let mut rewound = 0;
+        while rewound < n && self.interp.step_back() {
+            rewound += 1;
+        }
+        if rewound == 0 {
+            self.post_error("replaysteps: nothing to rewind".to_string());
+            return;
+        }
+        self.replay_remaining = rewound;
+        self.replay_timer = self.options.replay_delay_frames;
+        self.post_info(format!("replaying {rewound} steps ({jumps} loop jumps)"));
+    }
+
+    // runs a copy of the interpreter so the live state is left alone
+    fn dry_run(&mut self, n: u64) {
+        let mut sim = self.interp.clone();
+        sim.enable_events();
+        sim.trace = None;
+        let (steps, output, outcome) = sim.run_steps(n);
+        let events = sim.take_events();
+        let count = |f: fn(&Event) -> bool| events.iter().filter(|e| f(e)).count();
+
+        let mut before = self.interp.mem.clone();
+        before.resize(sim.mem.len(), 0);
+        let changes = sim.tape_mismatches(&before);
+
+        let mut body = format!(
+            "steps run: {steps}\nstopped with: {outcome:?}\npc: {} -> {}\nptr: {} -> {}\noutput: {:?}\nwrites: {}, moves: {}, loop repeats: {}\n",
+            self.interp.prog_ptr,
+            sim.prog_ptr,
+            self.interp.mem_ptr,
+            sim.mem_ptr,
+            sim.translate_output(&output),
+            count(|e| matches!(e, Event::Write { .. })),
+            count(|e| matches!(e, Event::Move(_))),
+            count(|e| matches!(e, Event::LoopRepeat(_))),
+        );
+        if changes.is_empty() {
+            body.push_str("no cells would change");
+        } else {
+            body.push_str("cells that would change:");
+            for (cell, old, new) in changes {
+                body.push_str(&format!("\n  {cell}: {old} -> {new}"));
+            }
+        }
+        self.show_message(" Dry run ", body);
+    }
+
+    fn eval(&mut self, code: &str) {
+        self.memory_pages = 0;
+        if !code.bytes().any(is_instruction) {
+            self.post_info("no executable instructions in snippet".to_string());
+            return;
+        }
+
+        let complete = match self.interp.extend_prog_partial(code.as_bytes()) {
+            Ok(complete) => complete,
+            Err(e) => {
+                self.post_error(format!("eval: {e}"));
+                return;
+            }
+        };
+        self.lines
+            .push(ReplLine::new(ReplType::Code, code.to_string()));
+        if !complete {
+            self.post_info("eval: loop still open, waiting for ']'".to_string());
+            return;
+        }
+
+        // runs everything held back along with this snippet
+        let end = self.interp.prog.len();
+
+        let outcome = self.interp.run_to(end, self.options.run_step_limit);
+        self.flush_output();
+
+        match outcome {
+            StepOutcome::Continue if self.interp.prog_ptr < end => self.post_error(format!(
+                "eval stopped after {} steps",
+                self.options.run_step_limit
+            )),
+            // finishing the snippet finishes the program, that isn't worth a message
+            StepOutcome::Continue | StepOutcome::Halted => {}
+            outcome => self.report_outcome(outcome),
+        }
+    }
+
+    // moves any bytes the program has written into the transcript
+    // a line without its `\n` yet stays open and later output is appended to it
+    fn flush_output(&mut self) {
+        if self.interp.output().is_empty() {
+            return;
+        }
+
+        let output = self.interp.take_output();
+        // a newline mapped by :outmap is shown as its replacement instead of ending the line
+        let newline = !self.interp.output_table.contains_key(&b'\n');
+        let mut rest = output.as_slice();
+        while !rest.is_empty() {
+            let end = rest.iter().position(|&b| newline && b == b'\n');
+            let (chunk, next) = match end {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, &[][..]),
+            };
+            self.partial_output.extend_from_slice(chunk);
+            self.show_partial_output(end.is_some());
+            if end.is_some() {
+                self.partial_output.clear();
+                self.partial_line = None;
+            }
+            rest = next;
+        }
+    }
+
+    // writes the open output line into the transcript or output panel
+    fn show_partial_output(&mut self, ended: bool) {
+        let text = self.interp.translate_output(&self.partial_output);
+        let text = if ended {
+            text.strip_suffix('\r').unwrap_or(&text)
+        } else {
+            text.as_str()
+        };
+        let text = if self.options.carriage_return {
+            apply_carriage_returns(text)
+        } else {
+            text.to_string()
+        };
+
+        let in_panel = self.output_in_panel();
+        let shown = match self.partial_line {
+            Some(i) if in_panel => self.output_lines.get_mut(i),
+            Some(i) => self.lines.get_mut(i).map(|line| &mut line.text),
+            None => None,
+        };
+        match shown {
+            Some(shown) => *shown = text,
+            None if in_panel => {
+                self.partial_line = Some(self.output_lines.len());
+                self.output_lines.push(text);
+            }
+            None => {
+                self.partial_line = Some(self.lines.len());
+                self.lines.push(ReplLine::new(ReplType::Output, text));
+            }
+        }
+    }
+
+    // the output panel lives in the info area, output goes to the repl while that is hidden
+    fn output_in_panel(&self) -> bool {
+        self.options.output_panel && self.options.layout.shows_info()
+    }
+
+    // a partial line may be in the panel or the repl, so start a new one when that changes
+    fn set_layout(&mut self, layout: PanelLayout) {
+        self.options.layout = layout;
+        self.end_partial_output();
+    }
+
+    // the next output starts a new line, used when the lines it would go on are cleared
+    fn end_partial_output(&mut self) {
+        self.partial_output.clear();
+        self.partial_line = None;
+    }
+
+    fn post_error(&mut self, err_str: String) {
+        if self.options.error_popup {
+            self.show_message(" Error ", err_str);
+            return;
+        }
+        self.frames_since_error = Some(0);
+        self.error_str = err_str;
+        self.error_is_info = false;
+    }
+
+    // same as post_error but shown without the error styling
+    fn post_info(&mut self, info_str: String) {
+        self.frames_since_error = Some(0);
+        self.error_str = info_str;
+        self.error_is_info = true;
+    }
+
+    fn force_quit(&mut self) {
+        self.running_mode = RunningMode::Exiting;
+    }
+
+    fn try_quit(&mut self) {
+        self.force_quit();
+    }
+
+    // program text as runs of spans, control bytes become spaces so offsets match columns
+    fn program_spans(&self) -> Vec<Span<'static>> {
+        let style_at = |pc: usize| {
+            if self.options.dim_executed && self.interp.pc_counts[pc] > 0 {
+                THEME.program_executed
+            } else {
+                THEME.root
+            }
+        };
+
+        let tab_width = self.options.tab_width;
+        let mut column = 0;
+        self.interp
+            .prog
+            .iter()
+            .enumerate()
+            .chunk_by(|&(pc, _)| style_at(pc))
+            .into_iter()
+            .map(|(style, run)| {
+                let mut text = String::new();
+                for (_, &b) in run {
+                    let width = display_width(b, column, tab_width);
+                    column += width;
+                    if b < b' ' {
+                        text.push_str(&" ".repeat(width));
+                    } else {
+                        text.push(b as char);
+                    }
+                }
+                Span::from(text).style(style)
+            })
+            .collect()
+    }
+
+    fn render_output_panel(&self, area: Rect, buf: &mut Buffer) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let start = self.output_lines.len().saturating_sub(visible);
+        Paragraph::new(
+            self.output_lines[start..]
+                .iter()
+                .map(|l| Line::from(l.as_str()))
+                .collect::<Vec<Line>>(),
+        )
+        .block(
+            Block::bordered()
+                .border_style(THEME.root)
+                .title("Output")
+                .title_style(THEME.root)
+                .style(THEME.root)
+                .border_type(BorderType::Rounded),
+        )
+        .render(area, buf);
+    }
+
+    fn render_info(&self, area: Rect, buf: &mut Buffer) {
+        Paragraph::new(vec![
+            Line::from(format!(
+                "tape: {} cells, highest used {}",
+                self.interp.mem.len(),
+                self.interp.high_water_mark()
+            )),
+            Line::from(format!("pointer: cell {}", self.interp.mem_ptr)),
+            Line::from(format!(
+                "instructions run: {}",
+                self.interp.instruction_counts().iter().sum::<u64>()
+            )),
+            Line::from(format!("loop depth: {}", self.interp.current_depth())),
+            Line::from(format!("input queued: {} bytes", self.interp.input.len())),
+            Line::from(format!(
+                "output: {} bytes{}",
+                self.interp.output_count,
+                if self.interp.output_enabled() {
+                    ""
+                } else {
+                    " (muted)"
+                }
+            )),
+        ])
+        .block(
+            Block::bordered()
+                .border_style(THEME.root)
+                .title("Info")
+                .title_style(THEME.root)
+                .style(THEME.root)
+                .border_type(BorderType::Rounded),
+        )
+        .render(area, buf);
+    }
+
+    fn render_memory(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .border_style(THEME.root)
+            .title("Memory")
+            .title_style(THEME.root)
+            .style(THEME.root)
+            .border_type(BorderType::Rounded);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let view = self.options.memory_view;
+        let mem = &self.interp.mem;
+        let mem_ptr = self.interp.mem_ptr;
+        let width = self.interp.cell_width;
+        let per_row = cells_per_row(inner.width, view, width);
+        let columns = cell_width(view, width);
+        let ptr_row = mem_ptr / per_row;
+        let marker_rows = if self.options.pointer_marker { 1 } else { 0 };
+        let visible_rows = (inner.height as usize).saturating_sub(marker_rows).max(1);
+        let total_rows = mem.len().div_ceil(per_row);
+        self.memory_grid.set((per_row, visible_rows));
+        let first_row = memory_first_row(ptr_row, visible_rows, total_rows, self.memory_pages);
+
+        let mut lines = Vec::new();
+        for row in first_row..first_row + visible_rows {
+            let row_start = row * per_row;
+            if row_start >= mem.len() {
+                break;
+            }
+            let row_end = (row_start + per_row).min(mem.len());
+
+            let spans: Vec<Span> = mem[row_start..row_end]
+                .iter()
+                .enumerate()
+                .flat_map(|(i, &cell)| {
+                    let style = if row_start + i == mem_ptr {
+                        THEME.memory_active
+                    } else {
+                        THEME.root
+                    };
+                    let text = format_cell(cell, view, width);
+                    [
+                        Span::from(format!("{text:>columns$}")).style(style),
+                        Span::from(" "),
+                    ]
+                })
+                .collect();
+            lines.push(Line::from(spans));
+
+            if self.options.pointer_marker && row == ptr_row {
+                let column = marker_column(row_start, mem_ptr, view, width);
+                lines.push(Line::from(" ".repeat(column) + "^"));
+            }
+        }
+        Paragraph::new(lines).render(inner, buf);
+    }
+
+    fn render_title_bar(&self, area: Rect, buf: &mut Buffer) {
+        let horizontal = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(9),
+            Constraint::Length(10),
+        ]);
+        let [app_name, editing_mode_area, repl_mode_area] = horizontal.areas(area);
+
+        //Block::new().style(THEME.root).render(area, buf);
+        match &self.session_name {
+            Some(name) => Paragraph::new(format!("BFRepl - {name}")).render(app_name, buf),
+            None => Paragraph::new("BFRepl").render(app_name, buf),
+        }
+        match self.mode {
+            Mode::Normal => Span::from(" Normal ").style(THEME.mode.normal),
+            Mode::Editing => Span::from(" Editing ").style(THEME.mode.editing),
+            Mode::Command => Span::from(" Command ").style(THEME.mode.command),
+            Mode::Input => Span::from("  Input  ").style(THEME.mode.input),
+        }
+        .render(editing_mode_area, buf);
+        match self.interp.state {
+            InterpState::Running => Span::from(" Running  ").style(THEME.mode.editing),
+            InterpState::Finished => Span::from(" Finished ").style(THEME.mode.normal),
+            InterpState::Waiting => Span::from(" Waiting  ").style(THEME.mode.command),
+        }
+        .render(repl_mode_area, buf);
+    }
+
+    /*
+        need to only render useable controls for currently selected tab.
+        so render common followed by specific controls.
+    */
+    fn render_bottom_bar(&self, area: Rect, buf: &mut Buffer) {
+        let spans: Vec<Span> = KEY_BINDINGS[..COMMON_KEYS]
+            .iter()
+            .flat_map(|(key, desc)| {
+                let key = Span::from(format!(" {key} ")).style(THEME.key_bind);
+                let desc = Span::from(format!(" {desc} ")).style(THEME.key_desc);
+                [key, desc]
+            })
+            .collect();
+
+        Line::from(spans).centered().render(area, buf);
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|a| a == "--run") {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("--run: missing program path (use - for stdin)");
+            std::process::exit(2);
+        };
+        let eof = match args
+            .iter()
+            .position(|a| a == "--eof")
+            .map(|i| args.get(i + 1))
+        {
+            None => EofBehavior::Wait,
+            Some(Some(v)) if v == "keep" => EofBehavior::Unchanged,
+            Some(Some(v)) if v == "zero" => EofBehavior::Zero,
+            Some(Some(v)) if v == "max" => EofBehavior::Max,
+            Some(_) => {
+                eprintln!("--eof: expected keep, zero or max");
+                std::process::exit(2);
+            }
+        };
+        let prog = headless::read_program(path, &mut io::stdin())?;
+        // a program piped through stdin leaves nothing behind for `,` to read
+        let interp = headless::run(&prog, &mut io::stdin(), &mut io::stdout(), eof)?;
+        if let Some(i) = args.iter().position(|a| a == "--dump-state") {
+            let json = state::state_to_json(&interp);
+            match args.get(i + 1).map(String::as_str) {
+                None | Some("-") => println!("{json}"),
+                Some(path) => std::fs::write(path, json + "\n")?,
+            }
+        }
+        return Ok(());
+    }
+
+    let mut terminal = tui::init()?;
+    let mut app = App::new();
+    app.run(&mut terminal)?;
+    tui::restore()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_command(app: &mut App, command: &str) {
+        app.command_field.set_text(command.to_string());
+        app.process_command();
+    }
+
+    fn transcript(app: &App) -> Vec<&str> {
+        app.lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn output_split_across_steps_stays_on_one_line() {
+        let mut app = App::new();
+        app.interp
+            .extend_prog(b"+++++++[>++++++++++<-]>++.+.>++++++++++.")
+            .unwrap();
+        while app.interp.state == InterpState::Running {
+            app.step_once();
+        }
+        assert_eq!(transcript(&app), ["HI"]);
+    }
+
+    // writes each byte with its own `.` step so every byte is a separate flush
+    fn emit(app: &mut App, bytes: &[u8]) {
+        for &byte in bytes {
+            app.interp.poke(0, byte as u32);
+            app.interp.extend_prog(b".").unwrap();
+            app.step_once();
+        }
+    }
+
+    #[test]
+    fn multibyte_output_split_across_flushes_is_joined() {
+        let mut app = App::new();
+        emit(&mut app, "é\n".as_bytes());
+        assert_eq!(transcript(&app), ["é"]);
+    }
+
+    #[test]
+    fn carriage_returns_apply_across_flushes() {
+        let mut app = App::new();
+        app.options.carriage_return = true;
+        emit(&mut app, b"AB\rC");
+        assert_eq!(transcript(&app), ["CB"]);
+    }
+
+    #[test]
+    fn eval_runs_a_loop_split_across_snippets() {
+        let mut app = App::new();
+        run_command(&mut app, "eval +++[");
+        assert!(app.interp.prog.is_empty());
+        run_command(&mut app, "eval ->+<]");
+        assert_eq!(&app.interp.mem[..2], [0, 3]);
+    }
+
+    #[test]
+    fn loading_a_program_starts_a_fresh_run() {
+        let mut app = App::new();
+        run_command(&mut app, "eval +>++,");
+        app.load_program(b"-");
+        assert_eq!(app.interp.step_count, 0);
+        assert_eq!((app.interp.prog_ptr, app.interp.mem_ptr), (0, 0));
+        assert_eq!(app.interp.state, InterpState::Running);
+        assert_eq!(app.interp.instruction_counts(), [0; 8]);
+        assert!(!app.interp.step_back());
+    }
+
+    #[test]
+    fn examples_list_loads_the_chosen_program() {
+        let mut app = App::new();
+        run_command(&mut app, "examples");
+        assert!(app.dialogue == Dialogue::Examples);
+        assert_eq!(app.examples_popup.options.len(), EXAMPLES.len());
+
+        app.dispatch_input(KeyCode::Down, KeyModifiers::NONE);
+        app.dispatch_input(KeyCode::Enter, KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+        assert_eq!(app.interp.prog, EXAMPLES[1].1);
+    }
+
+    #[test]
+    fn canceling_the_examples_list_keeps_the_program() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"+").unwrap();
+        run_command(&mut app, "examples");
+        app.dispatch_input(KeyCode::Esc, KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+        assert_eq!(app.interp.prog, b"+");
+    }
+
+    #[test]
+    fn set_eof_changes_the_policy_and_rejects_unknown_values() {
+        let mut app = App::new();
+        run_command(&mut app, "set eof zero");
+        assert_eq!(app.interp.eof_behavior, EofBehavior::Zero);
+        run_command(&mut app, "set eof never");
+        assert_eq!(app.interp.eof_behavior, EofBehavior::Zero);
+        assert!(!app.error_is_info);
+    }
+
+    #[test]
+    fn an_empty_input_line_queues_nothing() {
+        let mut app = App::new();
+        app.interp.extend_prog(b",").unwrap();
+        app.step_once();
+        app.mode = Mode::Input;
+        app.dispatch_input(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(app.interp.input.is_empty());
+        assert_eq!(app.interp.state, InterpState::Waiting);
+
+        run_command(&mut app, "set eof max");
+        app.step_once();
+        assert_eq!(app.interp.mem[0], 255);
+    }
+
+    #[test]
+    fn poke_rejects_a_cell_past_the_largest_tape() {
+        let mut app = App::new();
+        run_command(&mut app, &format!("poke 1=5,{MAX_TAPE_LEN}=1"));
+        assert_eq!(
+            app.error_str,
+            format!("poke: cell {MAX_TAPE_LEN} is past the tape limit")
+        );
+        assert_eq!(app.interp.mem[1], 0);
+        assert!(app.interp.mem.len() < MAX_TAPE_LEN);
+
+        run_command(&mut app, "poke 2000=7");
+        assert_eq!(app.interp.mem[2000], 7);
+    }
+
+    #[test]
+    fn snap_save_restore_list_and_delete() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"+++").unwrap();
+        app.interp.step();
+        run_command(&mut app, "snap save one");
+        app.interp.step();
+        run_command(&mut app, "snap save two");
+
+        run_command(&mut app, "snap restore one");
+        assert_eq!(app.interp.mem[0], 1);
+        run_command(&mut app, "snap list");
+        assert!(app.dialogue == Dialogue::Message);
+        assert_eq!(app.message_popup.body, "one\ntwo");
+
+        run_command(&mut app, "snap del one");
+        assert!(!app.snapshots.contains_key("one"));
+        run_command(&mut app, "snap restore one");
+        assert_eq!(app.error_str, "snap: no snapshot named one");
+    }
+
+    #[test]
+    fn dry_run_counts_events_without_touching_the_live_state() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"++[>+<-]").unwrap();
+        run_command(&mut app, "dryrun 100");
+        assert!(app
+            .message_popup
+            .body
+            .contains("writes: 6, moves: 4, loop repeats: 1"));
+        assert_eq!(app.interp.step_count, 0);
+        assert!(app.interp.events.is_none());
+    }
+
+    #[test]
+    fn eval_stops_at_a_breakpoint() {
+        let mut app = App::new();
+        run_command(&mut app, "break 2");
+        run_command(&mut app, "eval +++++");
+        assert_eq!(app.interp.mem[0], 2);
+        assert_eq!(app.error_str, "stopped at breakpoint 2");
+    }
+
+    #[test]
+    fn eval_reports_a_visit_limit() {
+        let mut app = App::new();
+        run_command(&mut app, "set visitlimit 10");
+        run_command(&mut app, "eval +[]");
+        assert_eq!(app.program_mark, Some(2));
+        assert!(app.error_str.starts_with("offset 2 ran more than 10 times"));
+    }
+
+    #[test]
+    fn eval_stops_at_the_pointer_limit() {
+        let mut app = App::new();
+        run_command(&mut app, "set ptrlimit 2");
+        run_command(&mut app, "eval >>>>>+");
+        assert_eq!(app.interp.mem_ptr, 3);
+        assert_eq!(app.interp.prog_ptr, 3);
+        assert_eq!(
+            app.error_str,
+            "memory pointer moved past the limit to cell 3"
+        );
+    }
+
+    #[test]
+    fn output_panel_falls_back_to_the_repl_without_an_info_area() {
+        let mut app = App::new();
+        run_command(&mut app, "set outpanel on");
+        run_command(&mut app, "layout noinfo");
+        emit(&mut app, b"A\n");
+        assert!(transcript(&app).contains(&"A"));
+        assert!(app.output_lines.is_empty());
+
+        run_command(&mut app, "layout split");
+        emit(&mut app, b"B\n");
+        assert_eq!(app.output_lines, ["B"]);
+        assert!(!transcript(&app).contains(&"B"));
+    }
+
+    #[test]
+    fn input_command_feeds_comma_reads() {
+        let mut app = App::new();
+        run_command(&mut app, "input AB");
+        assert_eq!(app.interp.input, b"AB");
+        assert_eq!(transcript(&app), ["AB"]);
+
+        app.interp.extend_prog(b",>,").unwrap();
+        app.run_until_input();
+        assert_eq!(&app.interp.mem[..2], &[65, 66]);
+        assert!(app.interp.input.is_empty());
+    }
+
+    #[test]
+    fn eval_after_stepping_maps_the_new_loop_at_absolute_offsets() {
+        let mut app = App::new();
+        run_command(&mut app, "eval +++");
+        run_command(&mut app, "eval [>+<-]");
+        assert!(app.interp.loop_map.contains(&(3, 8)));
+        assert_eq!(&app.interp.mem[..2], &[0, 3]);
+        assert_eq!(app.interp.prog_ptr, app.interp.prog.len());
+    }
+
+    #[test]
+    fn eval_grows_the_tape_past_its_initial_cells() {
+        let mut app = App::new();
+        run_command(&mut app, &format!("eval {}+", ">".repeat(1000)));
+        assert_eq!(app.interp.mem_ptr, 1000);
+        assert_eq!(app.interp.mem.len(), 1001);
+        assert_eq!(app.interp.mem[1000], 1);
+        assert!(app.error_str.is_empty());
+    }
+
+    #[test]
+    fn step_command_runs_up_to_the_count() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"+[]").unwrap();
+        run_command(&mut app, "step 500");
+        assert_eq!(app.interp.step_count, 500);
+        assert_eq!(app.error_str, "ran 500 steps");
+
+        let mut app = App::new();
+        app.interp.extend_prog(b"+++").unwrap();
+        run_command(&mut app, "step 500");
+        assert_eq!(app.interp.step_count, 3);
+        assert_eq!(app.error_str, "program finished");
+    }
+
+    #[test]
+    fn break_command_stops_a_run_inside_a_loop() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"++++[>+++<-]").unwrap();
+        run_command(&mut app, "break 6");
+        run_command(&mut app, "run");
+        assert_eq!((app.interp.prog_ptr, app.interp.mem[1]), (6, 0));
+        assert_eq!(app.error_str, "stopped at breakpoint 6");
+
+        run_command(&mut app, "continue");
+        assert_eq!((app.interp.prog_ptr, app.interp.mem[1]), (6, 3));
+    }
+
+    #[test]
+    fn reset_command_confirms_then_rewinds_and_clears_the_repl() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"+>+.").unwrap();
+        app.run_until_input();
+        assert!(!app.lines.is_empty());
+
+        run_command(&mut app, "reset");
+        assert!(app.dialogue == Dialogue::Reset);
+        app.dispatch_input(KeyCode::Char('y'), KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+        assert_eq!((app.interp.prog_ptr, app.interp.mem_ptr), (0, 0));
+        assert_eq!(app.interp.step_count, 0);
+        assert!(app.lines.is_empty());
+
+        // nothing has run since, so there is nothing to confirm
+        run_command(&mut app, "reset");
+        assert!(app.dialogue == Dialogue::None);
+    }
+
+    #[test]
+    fn format_write_keeps_breakpoints_on_their_instructions() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"+[-]").unwrap();
+        run_command(&mut app, "break 2");
+        app.program_mark = Some(3);
+        run_command(&mut app, "format write");
+
+        let prog = app.interp.prog.clone();
+        assert_ne!(prog, b"+[-]");
+        let minus = prog.iter().position(|&b| b == b'-').unwrap();
+        assert_eq!(app.interp.breakpoints.iter().collect::<Vec<_>>(), [&minus]);
+        assert_eq!(app.program_mark, None);
+    }
+
+    #[test]
+    fn paste_goes_into_the_active_field_on_one_line() {
+        let mut app = App::new();
+        app.paste("ignored");
+        assert_eq!(app.command_field.get_str(), "");
+
+        app.mode = Mode::Command;
+        app.command_field.insert_str("load ");
+        app.paste("a.bf\nb");
+        assert_eq!(app.command_field.get_str(), "load a.bf b");
+        assert_eq!(app.command_field.get_cursor_pos(), 11);
+    }
+
+    #[test]
+    fn split_args_handles_plain_and_quoted_args() {
+        assert_eq!(
+            split_args("load  a.bf b"),
+            Ok(vec!["load".into(), "a.bf".into(), "b".into()])
+        );
+        assert_eq!(
+            split_args(r#"load "my programs/a.bf""#),
+            Ok(vec!["load".into(), "my programs/a.bf".into()])
+        );
+        assert_eq!(split_args(r#""""#), Ok(vec![String::new()]));
+        assert_eq!(
+            split_args(r#"load "a.bf"#),
+            Err("unterminated quote".to_string())
+        );
+    }
+
+    #[test]
+    fn every_command_with_arguments_uses_the_tokenizer() {
+        let mut app = App::new();
+        run_command(&mut app, r#"name "my  session""#);
+        assert_eq!(app.session_name.as_deref(), Some("my  session"));
+        run_command(&mut app, "name plain words");
+        assert_eq!(app.session_name.as_deref(), Some("plain words"));
+
+        run_command(&mut app, r#"outmap 10 " | ""#);
+        assert_eq!(app.interp.output_table[&10], " | ");
+
+        app.interp.extend_prog(b"++").unwrap();
+        run_command(&mut app, r#"annotate 1 "a  b""#);
+        assert_eq!(app.interp.prog, b"+a  b+");
+
+        run_command(&mut app, "poke 1=4 run");
+        assert_eq!(&app.interp.mem[..2], &[2, 4]);
+
+        for command in [
+            r#"name "x"#,
+            r#"outmap 1 "x"#,
+            r#"annotate 0 "x"#,
+            r#"poke "0=9"#,
+        ] {
+            run_command(&mut app, command);
+            assert!(app.error_str.ends_with("unterminated quote"), "{command}");
+        }
+        assert_eq!(app.interp.mem[0], 2);
+        assert_eq!(app.session_name.as_deref(), Some("plain words"));
+    }
+
+    #[test]
+    fn home_and_end_move_the_command_cursor() {
+        let mut app = App::new();
+        app.mode = Mode::Command;
+        for c in "step 5".chars() {
+            app.dispatch_input(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        assert!(app.dispatch_input(KeyCode::Home, KeyModifiers::NONE));
+        assert_eq!(app.command_field.get_cursor_pos(), 0);
+        assert!(app.dispatch_input(KeyCode::End, KeyModifiers::NONE));
+        assert_eq!(app.command_field.get_cursor_pos(), 6);
+    }
+
+    #[test]
+    fn undo_after_history_brings_back_the_typed_line() {
+        let mut app = App::new();
+        app.command_history.push("step 5".to_string());
+        app.mode = Mode::Command;
+        app.command_field.clear();
+        app.dispatch_input(KeyCode::Char('r'), KeyModifiers::NONE);
+        app.dispatch_input(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(app.command_field.get_str(), "step 5");
+
+        app.dispatch_input(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(app.command_field.get_str(), "r");
+    }
+
+    #[test]
+    fn a_run_pauses_when_the_pointer_crosses_the_limit() {
+        let mut app = App::new();
+        run_command(&mut app, "set ptrlimit 3");
+        app.interp.extend_prog(b"+[>+]").unwrap();
+        run_command(&mut app, "run");
+        assert_eq!(app.interp.mem_ptr, 4);
+        assert_eq!(app.interp.mem[4], 0);
+        assert_eq!(
+            app.error_str,
+            "memory pointer moved past the limit to cell 4"
+        );
+
+        run_command(&mut app, "set ptrlimit off");
+        assert_eq!(app.interp.pointer_limit, None);
+    }
+
+    #[test]
+    fn frozen_cells_ignore_writes_by_default() {
+        let mut app = App::new();
+        run_command(&mut app, "freeze 1");
+        run_command(&mut app, "eval +>++>+");
+        assert_eq!(&app.interp.mem[..3], &[1, 0, 1]);
+        assert!(app.error_str.is_empty());
+
+        run_command(&mut app, "unfreeze 1");
+        run_command(&mut app, "eval <+");
+        assert_eq!(app.interp.mem[1], 1);
+    }
+
+    #[test]
+    fn frozen_cells_pause_under_eval_run_and_step() {
+        let mut app = App::new();
+        run_command(&mut app, "set frozen pause");
+        run_command(&mut app, "freeze 1");
+        run_command(&mut app, "eval +>++>+");
+        assert_eq!((app.interp.prog_ptr, app.interp.mem[1]), (3, 0));
+        assert_eq!(app.error_str, "write to frozen cell 1 at offset 2");
+
+        // the run picks up after the skipped write and stops at the next one
+        run_command(&mut app, "run");
+        assert_eq!((app.interp.prog_ptr, app.interp.mem[1]), (4, 0));
+        assert_eq!(app.error_str, "write to frozen cell 1 at offset 3");
+
+        app.error_str.clear();
+        app.step_once();
+        app.step_once();
+        assert_eq!(app.interp.mem[2], 1);
+        assert!(app.error_str.is_empty());
+
+        let mut app = App::new();
+        run_command(&mut app, "set frozen pause");
+        run_command(&mut app, "freeze 0");
+        app.interp.extend_prog(b"+").unwrap();
+        app.step_once();
+        assert_eq!(app.interp.mem[0], 0);
+        assert_eq!(app.error_str, "write to frozen cell 0 at offset 0");
+    }
+
+    #[test]
+    fn run_bang_passes_breakpoints_once_and_keeps_them() {
+        let mut app = App::new();
+        app.interp.extend_prog(b"++++[>+<-]").unwrap();
+        run_command(&mut app, "break 6");
+        run_command(&mut app, "watch 1");
+        run_command(&mut app, "run!");
+        assert_eq!(app.interp.state, InterpState::Finished);
+        assert_eq!(app.interp.mem[1], 4);
+        assert!(!app.interp.ignore_breakpoints);
+        assert!(app.interp.breakpoints.contains(&6));
+
+        run_command(&mut app, "reset");
+        app.dispatch_input(KeyCode::Char('y'), KeyModifiers::NONE);
+        app.update_dialogue();
+        run_command(&mut app, "run");
+        assert_eq!(app.interp.prog_ptr, 6);
+    }
+
+    #[test]
+    fn replaysteps_rewinds_then_replays_back_to_the_same_state() {
+        let mut app = App::new();
+        run_command(&mut app, "trace on 100");
+        app.interp.extend_prog(b"++[>+<-]>.").unwrap();
+        app.run_until_input();
+        let (mem, prog_ptr, steps) = (
+            app.interp.mem.clone(),
+            app.interp.prog_ptr,
+            app.interp.step_count,
+        );
+        let (trace, lines) = (app.interp.trace_csv(), app.lines.len());
+
+        run_command(&mut app, "replaysteps 8");
+        assert_eq!(app.replay_remaining, 8);
+        assert_eq!(app.interp.step_count, steps - 8);
+        assert_eq!(app.error_str, "replaying 8 steps (1 loop jumps)");
+
+        app.options.replay_delay_frames = 0;
+        while app.replay_remaining > 0 {
+            app.replay_frame();
+        }
+        assert_eq!(app.interp.step_count, steps);
+        assert_eq!(app.interp.mem, mem);
+        assert_eq!(app.interp.prog_ptr, prog_ptr);
+        assert_eq!(app.interp.trace_csv(), trace);
+        assert_eq!(app.lines.len(), lines);
+    }
+
+    #[test]
+    fn load_reports_a_missing_argument_or_file() {
+        let mut app = App::new();
+        run_command(&mut app, "load");
+        assert_eq!(app.error_str, "load: missing path argument");
+
+        run_command(&mut app, "load no/such/file.bf");
+        assert!(app.error_str.starts_with("no/such/file.bf: "));
+        assert!(app.interp.prog.is_empty());
+
+        run_command(&mut app, "load programs/hello.bf");
+        assert_eq!(app.interp.prog, EXAMPLES[0].1);
+    }
+
+    #[test]
+    fn save_then_load_round_trips_the_program() {
+        let path = std::env::temp_dir().join(format!("bfrepl save {}.bf", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut app = App::new();
+        app.interp.extend_prog(b"+[->+<] copy\n").unwrap();
+        run_command(&mut app, &format!("save \"{path}\""));
+        assert_eq!(app.error_str, format!("13 bytes written to {path}"));
+
+        let mut loaded = App::new();
+        run_command(&mut loaded, &format!("load \"{path}\""));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.interp.prog, app.interp.prog);
+    }
+
+    fn type_command(app: &mut App, command: &str, finish: KeyCode) {
+        app.mode = Mode::Command;
+        app.history_pos = None;
+        app.command_field.clear();
+        for c in command.chars() {
+            app.dispatch_input(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        app.dispatch_input(finish, KeyModifiers::NONE);
+    }
+
+    #[test]
+    fn command_history_skips_repeats_and_cancelled_lines() {
+        let mut app = App::new();
+        type_command(&mut app, "step 1", KeyCode::Enter);
+        type_command(&mut app, "step 1", KeyCode::Enter);
+        type_command(&mut app, "step 2", KeyCode::Enter);
+        type_command(&mut app, "step 3", KeyCode::Esc);
+        assert_eq!(app.command_history, ["step 1", "step 2"]);
+    }
+
+    #[test]
+    fn up_and_down_cycle_through_history() {
+        let mut app = App::new();
+        type_command(&mut app, "step 1", KeyCode::Enter);
+        type_command(&mut app, "step 2", KeyCode::Enter);
+        type_command(&mut app, "", KeyCode::Null);
+
+        let mut shown = Vec::new();
+        for key in [
+            KeyCode::Up,
+            KeyCode::Up,
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Down,
+        ] {
+            app.dispatch_input(key, KeyModifiers::NONE);
+            shown.push(app.command_field.get_str().to_string());
+        }
+        assert_eq!(shown, ["step 2", "step 1", "step 1", "step 2", ""]);
+    }
+
+    // a file under the temp dir that the caller removes, unique to this test process
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("bfrepl-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn tape_load_seeds_the_leading_cells() {
+        let path = temp_file("tape", &[3, 1, 4]);
+        let mut app = App::new();
+        app.interp.mem[5] = 9;
+        run_command(&mut app, &format!("tape load {path}"));
+        // only the leading cells are replaced
+        assert_eq!(&app.interp.mem[..6], &[3, 1, 4, 0, 0, 9]);
+        assert_eq!(app.interp.mem_ptr, 0);
+
+        run_command(&mut app, &format!("tape load {path} 2"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(app.interp.mem_ptr, 2);
+
+        run_command(&mut app, "tape load");
+        assert_eq!(app.error_str, "tape load: missing path");
+    }
+
+    #[test]
+    fn a_program_of_only_comments_says_so() {
+        let path = temp_file("comments", b"just a note\n");
+        let mut app = App::new();
+        run_command(&mut app, &format!("load {path}"));
+        std::fs::remove_file(&path).unwrap();
+        app.step_once();
+        assert_eq!(app.error_str, "no executable instructions in program");
+        assert!(app.error_is_info);
+        assert_eq!(app.interp.step_count, 0);
+
+        run_command(&mut app, "eval a b c");
+        assert_eq!(app.error_str, "no executable instructions in snippet");
+        assert_eq!(app.interp.prog, b"just a note\n");
+    }
+
+    #[test]
+    fn adjusting_the_cell_follows_the_arithmetic_mode() {
+        let mut app = App::new();
+        app.interp.poke(0, 255);
+        app.interp.adjust_cell(true);
+        assert_eq!(app.interp.mem[0], 0);
+        app.interp.adjust_cell(false);
+        assert_eq!(app.interp.mem[0], 255);
+
+        run_command(&mut app, "set arith sat");
+        app.interp.adjust_cell(true);
+        assert_eq!(app.interp.mem[0], 255);
+        app.interp.poke(0, 0);
+        app.interp.adjust_cell(false);
+        assert_eq!(app.interp.mem[0], 0);
+        // a poke is not an executed instruction
+        assert_eq!(app.interp.step_count, 0);
+    }
+
+    #[test]
+    fn progdiff_lists_the_edits_against_a_file() {
+        let path = temp_file("progdiff", b"++-+");
+        let mut app = App::new();
+        run_command(&mut app, "eval +++");
+        run_command(&mut app, &format!("progdiff {path}"));
+        assert!(app.dialogue == Dialogue::Message);
+        assert_eq!(app.message_popup.body, "+      2  -");
+
+        app.load_program(b"+ + - +");
+        run_command(&mut app, &format!("progdiff {path}"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            app.error_str,
+            format!("no instruction differences with {path}")
+        );
+
+        run_command(&mut app, "progdiff");
+        assert_eq!(app.error_str, "progdiff: missing path");
+    }
+
+    #[test]
+    fn runin_pauses_at_the_first_read_with_an_empty_queue() {
+        let mut app = App::new();
+        app.load_program(b"+++>,<,");
+        run_command(&mut app, "runin");
+        assert_eq!(app.interp.prog_ptr, 4);
+        assert_eq!(app.interp.mem[..2], [3, 0]);
+        assert_eq!(app.mode, Mode::Input);
+
+        // with a byte queued the first read passes and the second one stops
+        app.mode = Mode::Normal;
+        app.interp.push_input(b"x");
+        run_command(&mut app, "runin");
+        assert_eq!(app.interp.prog_ptr, 6);
+        assert_eq!(app.interp.mem[1], b'x' as u32);
+        assert_eq!(app.mode, Mode::Input);
+    }
+
+    #[test]
+    fn memview_switches_the_cell_radix() {
+        let mut app = App::new();
+        let shown = |app: &App| format_cell(255, app.options.memory_view, CellWidth::U8);
+        assert_eq!(shown(&app), "255");
+        run_command(&mut app, "memview hex");
+        assert_eq!(shown(&app), "FF");
+        run_command(&mut app, "memview bin");
+        assert_eq!(shown(&app), "11111111");
+        run_command(&mut app, "memview dec");
+        assert_eq!(shown(&app), "255");
+
+        run_command(&mut app, "memview oct");
+        assert_eq!(
+            app.error_str,
+            "memview: expected dec, hex, bin, glyph or ascii, got 'oct'"
+        );
+        assert!(app.options.memory_view == MemoryView::Decimal);
+    }
+
+    #[test]
+    fn hot_lists_the_loop_body_first() {
+        let mut app = App::new();
+        run_command(&mut app, "hot");
+        assert_eq!(app.error_str, "no instructions have been executed");
+
+        app.load_program(b"+++[->++<]>.");
+        run_command(&mut app, "run");
+        assert_eq!(app.interp.hotspots(3), [(4, 3), (5, 3), (6, 3)]);
+        run_command(&mut app, "hot 2");
+        assert_eq!(
+            app.message_popup.body,
+            "     4  -           3\n     5  >           3"
+        );
+
+        run_command(&mut app, "hot many");
+        assert_eq!(app.error_str, "hot: expected a count, got 'many'");
+    }
+
+    #[test]
+    fn output_off_counts_bytes_without_emitting_them() {
+        let mut app = App::new();
+        run_command(&mut app, "set output off");
+        run_command(&mut app, "eval ++++++++[>++++++++<-]>+..");
+        assert!(app.interp.output().is_empty());
+        assert!(app.output_lines.is_empty());
+        assert!(transcript(&app).iter().all(|line| !line.contains('A')));
+        assert_eq!(app.interp.output_count, 2);
+
+        run_command(&mut app, "set output on");
+        run_command(&mut app, "eval .");
+        assert_eq!(app.interp.output_count, 3);
+        assert_eq!(transcript(&app).last(), Some(&"A"));
+    }
+
+    #[test]
+    fn match_reports_the_partner_of_nested_brackets() {
+        let mut app = App::new();
+        app.load_program(b"+[>[-]<]");
+        run_command(&mut app, "match 1");
+        assert_eq!(app.error_str, "[ at 1 matches ] at 7");
+        assert_eq!(app.program_mark, Some(7));
+        run_command(&mut app, "match 5");
+        assert_eq!(app.error_str, "] at 5 matches [ at 3");
+        assert_eq!(app.program_mark, Some(3));
+
+        run_command(&mut app, "match 2");
+        assert_eq!(app.error_str, "match: no bracket at offset 2");
+        assert!(!app.error_is_info);
+        assert_eq!(app.program_mark, Some(3));
+    }
+
+    #[test]
+    fn loop_depth_follows_the_pc_through_nested_loops() {
+        let mut app = App::new();
+        app.load_program(b"[[+]+]");
+        run_command(&mut app, "poke 0=255");
+        let mut seen = vec![(app.interp.prog_ptr, app.interp.current_depth())];
+        for _ in 0..5 {
+            run_command(&mut app, "step 1");
+            seen.push((app.interp.prog_ptr, app.interp.current_depth()));
+        }
+        // the brackets themselves sit outside the loop they delimit
+        assert_eq!(seen, [(0, 0), (1, 1), (2, 2), (3, 1), (4, 1), (5, 0)]);
+    }
+
+    #[test]
+    fn extent_reports_bounds_or_a_data_dependent_reach() {
+        let mut app = App::new();
+        app.load_program(b">>><<");
+        run_command(&mut app, "extent");
+        assert_eq!(app.interp.static_extent(), Some((0, 3)));
+        assert_eq!(app.error_str, "program reaches cells 0..=3");
+        assert!(app.error_is_info);
+
+        app.load_program(b"+[>]");
+        run_command(&mut app, "extent");
+        assert_eq!(app.interp.static_extent(), None);
+        assert_eq!(app.error_str, "tape extent depends on the data");
+
+        app.load_program(b"<[->+<]");
+        run_command(&mut app, "extent");
+        assert_eq!(
+            app.error_str,
+            "program reaches cells -1..=0, it moves left of the starting cell"
+        );
+        assert!(!app.error_is_info);
+    }
+
+    #[test]
+    fn record_writes_one_frame_per_step() {
+        let path = temp_file("record", b"");
+        let mut app = App::new();
+        app.load_program(b"+>+<-");
+        run_command(&mut app, &format!("record start {path}"));
+        run_command(&mut app, "run");
+        run_command(&mut app, "record stop");
+        assert_eq!(app.error_str, format!("5 frames written to {path}"));
+        assert!(app.recording.is_none() && app.interp.trace.is_none());
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let frames: Vec<&str> = written.lines().collect();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(
+            frames[0],
+            r#"{"step":1,"pc":0,"mem_ptr":0,"changed":[[0,1]]}"#
+        );
+        assert_eq!(frames[1], r#"{"step":2,"pc":1,"mem_ptr":1,"changed":[]}"#);
+    }
+
+    #[test]
+    fn a_recording_survives_loading_a_state() {
+        let state = temp_file("record-state", b"");
+        let path = temp_file("record-frames", b"");
+        let mut app = App::new();
+        app.load_program(b"++");
+        run_command(&mut app, &format!("state json {state}"));
+        run_command(&mut app, &format!("record start {path}"));
+        run_command(&mut app, "step 1");
+        run_command(&mut app, &format!("state load {state}"));
+        assert!(app.interp.trace.is_some());
+        run_command(&mut app, "run");
+        run_command(&mut app, "record stop");
+        std::fs::remove_file(&state).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written.lines().count(), 3);
+    }
+
+    #[test]
+    fn nextout_stops_right_after_each_output_byte() {
+        let mut app = App::new();
+        app.load_program(b"++++++++[>++++++++<-]>+.+.+.");
+        run_command(&mut app, "nextout");
+        assert_eq!(app.interp.prog_ptr, 24);
+        assert_eq!(transcript(&app).last(), Some(&"A"));
+
+        run_command(&mut app, "nextout");
+        assert_eq!(app.interp.prog_ptr, 26);
+        assert_eq!(transcript(&app).last(), Some(&"AB"));
+
+        run_command(&mut app, "nextout");
+        run_command(&mut app, "nextout");
+        assert_eq!(transcript(&app).last(), Some(&"ABC"));
+        assert_eq!(app.error_str, "program finished");
+    }
+
+    #[test]
+    fn clear_repl_empties_the_transcript_only() {
+        let mut app = App::new();
+        run_command(&mut app, "eval ++++++++[>++++++++<-]>+.");
+        run_command(&mut app, "poke 3=7");
+        app.repl_scroll = 1;
+        assert!(!app.lines.is_empty());
+
+        run_command(&mut app, "clear-repl");
+        assert!(app.lines.is_empty());
+        assert_eq!(app.repl_scroll, 0);
+        assert_eq!(app.interp.prog, b"++++++++[>++++++++<-]>+.");
+        assert_eq!(app.interp.mem[..4], [0, 65, 0, 7]);
+        assert_eq!(app.interp.mem_ptr, 1);
+    }
+
+    #[test]
+    fn pointer_marker_sits_under_the_active_cell() {
+        // a memory panel with 4 cells per row
+        fn render(app: &App) -> Buffer {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 18, 6));
+            app.render_memory(buf.area, &mut buf);
+            buf
+        }
+        fn caret(buf: &Buffer) -> Option<Position> {
+            buf.area.positions().find(|&pos| buf[pos].symbol() == "^")
+        }
+
+        let mut app = App::new();
+        app.interp.poke(5, 200);
+        app.interp.mem_ptr = 5;
+        assert_eq!(marker_column(4, 5, MemoryView::Decimal, CellWidth::U8), 5);
+        let buf = render(&app);
+        let pos = caret(&buf).unwrap();
+        // one border column, then cell 4 and its separator before cell 5
+        assert_eq!(pos.x, 1 + 5);
+        let above: String = (5..8).map(|x| buf[(x, pos.y - 1)].symbol()).collect();
+        assert_eq!(above, "200");
+
+        run_command(&mut app, "set marker off");
+        assert_eq!(caret(&render(&app)), None);
+    }
+
+    #[test]
+    fn hash_ignores_comments_but_not_instructions() {
+        let mut app = App::new();
+        app.load_program(b"+[->+<] add");
+        run_command(&mut app, "hash");
+        let first = app.error_str.clone();
+        assert!(first.starts_with("instruction hash: "));
+
+        app.load_program(b"copy:\n  + [ - > + < ]\n");
+        run_command(&mut app, "hash");
+        assert_eq!(app.error_str, first);
+
+        app.load_program(b"+[->-<]");
+        run_command(&mut app, "hash");
+        assert_ne!(app.error_str, first);
+    }
+
+    #[test]
+    fn a_run_pauses_before_the_tape_grows_past_the_limit() {
+        let mut app = App::new();
+        run_command(&mut app, "set tapelimit 1500");
+        app.load_program(b"+[>+]");
+        run_command(&mut app, "run");
+        assert_eq!(
+            app.error_str,
+            "tape would grow past the limit of 1500 cells"
+        );
+        assert_eq!(app.interp.mem_ptr, 1499);
+        assert_eq!(app.interp.prog_ptr, 2);
+        assert!(app.interp.mem.len() <= 1500);
+
+        run_command(&mut app, "set tapelimit off");
+        run_command(&mut app, "step 2");
+        assert_eq!(app.interp.mem_ptr, 1500);
+        assert_eq!(app.interp.mem[1500], 1);
+    }
+
+    #[test]
+    fn feedcell_queues_the_current_cell() {
+        let mut app = App::new();
+        run_command(&mut app, "poke 2=72");
+        app.interp.mem_ptr = 2;
+        run_command(&mut app, "feedcell");
+        assert_eq!(app.error_str, "queued 72 as input");
+        assert_eq!(app.interp.input, [72]);
+
+        // the queued byte is what the next `,` reads
+        run_command(&mut app, "eval >,");
+        assert_eq!(app.interp.mem[3], 72);
+        assert_eq!(app.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn instructions_behind_the_pc_are_dimmed() {
+        let spans = |app: &App| -> Vec<(String, Style)> {
+            app.program_spans()
+                .into_iter()
+                .map(|span| (span.content.to_string(), span.style))
+                .collect()
+        };
+
+        let mut app = App::new();
+        app.load_program(b"+++>--");
+        run_command(&mut app, "step 3");
+        assert_eq!(
+            spans(&app),
+            [
+                ("+++".to_string(), THEME.program_executed),
+                (">--".to_string(), THEME.root),
+            ]
+        );
+
+        run_command(&mut app, "set dim off");
+        assert_eq!(spans(&app), [("+++>--".to_string(), THEME.root)]);
+    }
+
+    #[test]
+    fn expect_tape_compares_against_a_file() {
+        let path = temp_file("expect", &[3, 0, 4]);
+        let mut app = App::new();
+        run_command(&mut app, "eval +++>>++++");
+        run_command(&mut app, &format!("expect tape {path}"));
+        assert_eq!(app.error_str, format!("tape matches {path} over 3 cells"));
+        assert!(app.error_is_info);
+
+        run_command(&mut app, "eval <<-->+");
+        run_command(&mut app, &format!("expect tape {path}"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(app.dialogue == Dialogue::Message);
+        assert_eq!(
+            app.message_popup.title,
+            format!(" 2 cells differ from {path} ")
+        );
+        assert_eq!(
+            app.message_popup.body,
+            "cell      0: expected   3, found   1\ncell      1: expected   0, found   1"
+        );
+
+        run_command(&mut app, "expect tape");
+        assert_eq!(app.error_str, "expect tape: missing path");
+        run_command(&mut app, "expect memory x");
+        assert_eq!(app.error_str, "Unknown expect command: memory");
+    }
+
+    #[test]
+    fn q_asks_before_quitting_when_confirmquit_is_on() {
+        let mut app = App::new();
+        run_command(&mut app, "set confirmquit on");
+        app.normal_key(KeyCode::Char('q'), KeyModifiers::NONE);
+        assert!(app.dialogue == Dialogue::Quit);
+        assert!(app.running_mode != RunningMode::Exiting);
+
+        // answering no keeps the app running
+        app.dispatch_input(KeyCode::Char('n'), KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+        assert!(app.running_mode != RunningMode::Exiting);
+
+        app.normal_key(KeyCode::Char('q'), KeyModifiers::NONE);
+        app.dispatch_input(KeyCode::Char('y'), KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.running_mode == RunningMode::Exiting);
+
+        let mut app = App::new();
+        run_command(&mut app, "set confirmquit on");
+        run_command(&mut app, "q!");
+        assert!(app.running_mode == RunningMode::Exiting);
+
+        let mut app = App::new();
+        app.normal_key(KeyCode::Char('q'), KeyModifiers::NONE);
+        assert!(app.running_mode == RunningMode::Exiting);
+    }
+
+    #[test]
+    fn loopinfo_reports_the_cells_a_loop_writes() {
+        let mut app = App::new();
+        app.load_program(b"+[->+<]");
+        run_command(&mut app, "loopinfo 1");
+        assert_eq!(app.message_popup.title, " Loop at 1 ");
+        assert_eq!(
+            app.message_popup.body,
+            "net pointer movement: +0\nreads cells:  +0 +1\nwrites cells: +0 +1"
+        );
+
+        run_command(&mut app, "loopinfo 2");
+        assert_eq!(app.error_str, "loopinfo: no loop starts at offset 2");
+    }
+
+    #[test]
+    fn outmap_shows_a_newline_as_its_escape() {
+        let mut app = App::new();
+        run_command(&mut app, r"outmap 10 \n");
+        assert_eq!(
+            app.interp.output_table.get(&10).map(String::as_str),
+            Some(r"\n")
+        );
+        emit(&mut app, b"A\nB");
+        assert_eq!(transcript(&app), [r"A\nB"]);
+
+        let mut app = App::new();
+        run_command(&mut app, r"outmap 10 \n");
+        run_command(&mut app, "outmap del 10");
+        assert!(app.interp.output_table.is_empty());
+        emit(&mut app, b"A\nB");
+        assert_eq!(transcript(&app), ["A", "B"]);
+    }
+
+    #[test]
+    fn continue_after_input_runs_to_the_end() {
+        let mut app = App::new();
+        app.load_program(b",+.,");
+        run_command(&mut app, "run");
+        assert_eq!(app.mode, Mode::Input);
+        assert_eq!(app.interp.state, InterpState::Waiting);
+
+        app.input_field.set_text("A".to_string());
+        app.submit_input();
+        run_command(&mut app, "continue");
+        assert_eq!(app.interp.state, InterpState::Finished);
+        assert_eq!(app.error_str, "program finished");
+        // the second read takes the newline the line was submitted with
+        assert_eq!(app.interp.mem[0], b'\n' as u32);
+        assert!(transcript(&app).contains(&"B"));
+
+        run_command(&mut app, "continue");
+        assert_eq!(app.error_str, "program finished");
+    }
+
+    #[test]
+    fn dry_run_reports_what_would_happen_and_changes_nothing() {
+        let mut app = App::new();
+        app.load_program(b">,.>++");
+        app.interp.push_input(b"Z");
+        run_command(&mut app, "step 1");
+        let lines_before = app.lines.len();
+        run_command(&mut app, "dryrun 4");
+        assert_eq!(
+            app.message_popup.body,
+            "steps run: 4\nstopped with: Continue\npc: 1 -> 5\nptr: 1 -> 2\noutput: \"Z\"\n\
+             writes: 2, moves: 1, loop repeats: 0\n\
+             cells that would change:\n  1: 0 -> 90\n  2: 0 -> 1"
+        );
+
+        assert_eq!((app.interp.prog_ptr, app.interp.mem_ptr), (1, 1));
+        assert_eq!(app.interp.step_count, 1);
+        assert_eq!(app.interp.mem[..3], [0, 0, 0]);
+        assert_eq!(app.interp.input, [b'Z']);
+        assert_eq!(app.interp.output_count, 0);
+        assert_eq!(app.lines.len(), lines_before);
+    }
+
+    #[test]
+    fn outsize_counts_dots_outside_loops() {
+        let mut app = App::new();
+        app.load_program(b"...");
+        run_command(&mut app, "outsize");
+        assert_eq!(app.interp.static_output_count(), (3, true));
+        assert_eq!(app.error_str, "program outputs 3 bytes");
+
+        app.load_program(b"[.]");
+        run_command(&mut app, "outsize");
+        assert_eq!(app.interp.static_output_count(), (0, false));
+        assert_eq!(
+            app.error_str,
+            "program outputs at least 0 bytes, loops make the rest indeterminate"
+        );
+    }
+
+    #[test]
+    fn errors_popup_shows_errors_in_a_dismissible_popup() {
+        let mut app = App::new();
+        run_command(&mut app, "set errors popup");
+        assert_eq!(app.error_str, "errors set to popup");
+        // let the confirmation time out of the status line
+        app.frames_since_error = None;
+        app.error_str.clear();
+        run_command(&mut app, "match x");
+        assert!(app.dialogue == Dialogue::Message);
+        assert_eq!(app.message_popup.title, " Error ");
+        assert_eq!(
+            app.message_popup.body,
+            "match: expected a program offset, got 'x'"
+        );
+        assert_eq!(app.frames_since_error, None);
+        assert!(app.error_str.is_empty());
+
+        app.dispatch_input(KeyCode::Enter, KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+
+        run_command(&mut app, "set errors status");
+        run_command(&mut app, "match x");
+        assert!(app.dialogue == Dialogue::None);
+        assert_eq!(app.frames_since_error, Some(0));
+    }
+
+    #[test]
+    fn inputview_marks_the_next_byte_to_be_read() {
+        let mut app = App::new();
+        run_command(&mut app, "inputview");
+        assert_eq!(app.error_str, "input queue is empty, 0 bytes read so far");
+
+        app.load_program(b",,");
+        app.interp.push_input(b"hello");
+        run_command(&mut app, "run");
+        run_command(&mut app, "inputview");
+        assert_eq!(app.message_popup.title, " Input queue ");
+        // offsets count every byte queued, so the dump starts at the two already read
+        assert_eq!(
+            app.message_popup.body,
+            format!("     2  {:<23}  llo\n        ^^ next", "6C 6C 6F")
+        );
+    }
+
+    #[test]
+    fn each_layout_splits_the_canvas() {
+        let canvas = Rect::new(0, 0, 100, 40);
+        let mut app = App::new();
+        assert_eq!(
+            app.options.layout.areas(canvas),
+            [
+                Rect::new(0, 0, 70, 40),
+                Rect::new(70, 0, 30, 30),
+                Rect::new(70, 30, 30, 10),
+            ]
+        );
+
+        run_command(&mut app, "layout noinfo");
+        assert_eq!(
+            app.options.layout.areas(canvas),
+            [
+                Rect::new(0, 0, 70, 40),
+                Rect::new(70, 0, 30, 40),
+                Rect::default()
+            ]
+        );
+
+        run_command(&mut app, "layout memory");
+        assert_eq!(
+            app.options.layout.areas(canvas),
+            [Rect::default(), canvas, Rect::default()]
+        );
+
+        // the key cycles back round to the split
+        app.normal_key(KeyCode::Char('l'), KeyModifiers::NONE);
+        assert_eq!(app.options.layout, PanelLayout::Split);
+    }
+
+    #[test]
+    fn ranges_reports_the_lowest_and_highest_value_of_each_cell() {
+        let mut app = App::new();
+        run_command(&mut app, "ranges");
+        assert_eq!(app.error_str, "ranges: tracking is off, run :ranges on");
+
+        run_command(&mut app, "ranges on");
+        run_command(&mut app, "eval >+++++---");
+        assert_eq!(app.interp.mem[1], 2);
+        run_command(&mut app, "ranges");
+        assert_eq!(
+            app.message_popup.body,
+            " cell   min   max\n    1     0     5"
+        );
+
+        // a reset starts the tracking over
+        app.reset();
+        run_command(&mut app, "ranges");
+        assert_eq!(app.error_str, "no cell has changed value yet");
+    }
+
+    #[test]
+    fn reset_key_rewinds_and_cannot_take_a_default_binding() {
+        let mut app = App::new();
+        app.load_program(b"+++>++");
+        run_command(&mut app, "run");
+        app.normal_key(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!((app.interp.prog_ptr, app.interp.mem_ptr), (0, 0));
+        assert!(app.interp.mem.iter().all(|&cell| cell == 0));
+        assert_eq!(app.interp.prog, b"+++>++");
+
+        run_command(&mut app, "set resetkey n");
+        assert_eq!(app.error_str, "resetkey: 'n' is already bound");
+        assert_eq!(
+            app.options.reset_key,
+            (KeyCode::Char('r'), KeyModifiers::CONTROL)
+        );
+        // n still steps
+        app.normal_key(KeyCode::Char('n'), KeyModifiers::NONE);
+        assert_eq!(app.interp.step_count, 1);
+
+        run_command(&mut app, "set resetkey x");
+        app.normal_key(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(app.interp.step_count, 0);
+        assert_eq!(app.interp.mem[0], 0);
+    }
+
+    #[test]
+    fn reachable_counts_what_the_pc_can_still_get_to() {
+        let mut app = App::new();
+        app.load_program(b"++>+[-]<.");
+        run_command(&mut app, "reachable");
+        assert_eq!(app.error_str, "9 of 9 instructions reachable from offset 0");
+
+        // the code already passed can't be reached again
+        run_command(&mut app, "step 3");
+        run_command(&mut app, "reachable");
+        assert_eq!(app.error_str, "6 of 9 instructions reachable from offset 3");
+
+        // inside a loop its body is reachable through the back jump, but not its `[`
+        app.load_program(b"+[->+<].");
+        run_command(&mut app, "step 3");
+        run_command(&mut app, "reachable");
+        assert_eq!(app.error_str, "6 of 8 instructions reachable from offset 3");
+    }
+
+    #[test]
+    fn name_shows_in_the_title_bar() {
+        fn title(app: &App) -> String {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 60, 1));
+            app.render_title_bar(buf.area, &mut buf);
+            (0..41).map(|x| buf[(x, 0)].symbol()).collect::<String>()
+        }
+
+        let mut app = App::new();
+        assert_eq!(title(&app).trim_end(), "BFRepl");
+        run_command(&mut app, r#"name "fib  check""#);
+        assert_eq!(app.session_name.as_deref(), Some("fib  check"));
+        assert_eq!(title(&app).trim_end(), "BFRepl - fib  check");
+
+        run_command(&mut app, "name");
+        assert_eq!(app.session_name, None);
+        assert_eq!(title(&app).trim_end(), "BFRepl");
+    }
+
+    // the text of each row of a rendered panel
+    fn rendered_rows(buf: &Buffer) -> Vec<String> {
+        (0..buf.area.height)
+            .map(|y| {
+                (0..buf.area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn info_rows(app: &App) -> Vec<String> {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 8));
+        app.render_info(buf.area, &mut buf);
+        rendered_rows(&buf)
+    }
+
+    #[test]
+    fn instruction_counts_add_up_for_a_loop() {
+        let mut app = App::new();
+        app.load_program(b"++[>+++<-]");
+        run_command(&mut app, "run");
+        // `>` `<` `+` `-` `.` `,` `[` `]`
+        assert_eq!(app.interp.instruction_counts(), [2, 2, 8, 2, 0, 0, 1, 2]);
+        assert!(info_rows(&app)[3].contains("instructions run: 17"));
+
+        app.reset();
+        assert_eq!(app.interp.instruction_counts(), [0; 8]);
+        assert!(info_rows(&app)[3].contains("instructions run: 0 "));
+    }
+
+    #[test]
+    fn info_panel_shows_the_tape_statistics() {
+        let mut app = App::new();
+        app.load_program(b">>>><<>");
+        run_command(&mut app, "run");
+        assert_eq!(app.interp.high_water_mark(), 4);
+        let rows = info_rows(&app);
+        assert!(rows[1].contains("tape: 1000 cells, highest used 4"));
+        assert!(rows[2].contains("pointer: cell 3"));
+
+        // moving the pointer by hand counts too
+        app.interp.mem_ptr = 9;
+        assert!(info_rows(&app)[1].contains("highest used 9"));
+    }
+
+    #[test]
+    fn memview_formats_the_tape_in_each_mode() {
+        // the first row of a memory panel wide enough for three cells of any view
+        fn first_row(app: &App) -> String {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 29, 4));
+            app.render_memory(buf.area, &mut buf);
+            rendered_rows(&buf)[1]
+                .trim_matches('│')
+                .trim_end()
+                .to_string()
+        }
+
+        let mut app = App::new();
+        app.interp.mem.truncate(3);
+        app.interp.mem.copy_from_slice(&[72, 105, 7]);
+        assert_eq!(first_row(&app), " 72 105   7");
+        run_command(&mut app, "memview hex");
+        assert_eq!(first_row(&app), "48 69 07");
+        run_command(&mut app, "memview ascii");
+        assert_eq!(first_row(&app), "H i .");
+
+        // the key steps through the views in order
+        run_command(&mut app, "memview dec");
+        app.normal_key(KeyCode::Char('m'), KeyModifiers::NONE);
+        assert!(app.options.memory_view == MemoryView::Hex);
+    }
+
+    #[test]
+    fn memory_panel_follows_the_pointer_and_pages_by_hand() {
+        // first cell drawn by a 4 cells per row panel with room for 3 rows and the marker
+        fn first_cell(app: &App) -> String {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 18, 6));
+            app.render_memory(buf.area, &mut buf);
+            rendered_rows(&buf)[1][3..]
+                .split_whitespace()
+                .next()
+                .unwrap()
+                .to_string()
+        }
+
+        let mut app = App::new();
+        app.load_program(b">>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+");
+        for cell in 0..100 {
+            app.interp.poke(cell, cell as u32);
+        }
+        run_command(&mut app, "step 40");
+        // the pointer's row is kept at the bottom of the window
+        assert_eq!(memory_first_row(10, 3, 250, 0), 8);
+        assert_eq!(first_cell(&app), "32");
+
+        app.normal_key(KeyCode::PageUp, KeyModifiers::NONE);
+        assert_eq!(first_cell(&app), "20");
+        app.normal_key(KeyCode::PageDown, KeyModifiers::NONE);
+        app.normal_key(KeyCode::PageDown, KeyModifiers::NONE);
+        assert_eq!(first_cell(&app), "44");
+
+        // stepping snaps back to the pointer
+        app.normal_key(KeyCode::Char('n'), KeyModifiers::NONE);
+        assert_eq!(app.interp.mem_ptr, 41);
+        assert_eq!(first_cell(&app), "32");
+    }
+
+    #[test]
+    fn program_view_scrolls_to_keep_the_caret_in_view() {
+        // the program text and the caret column of a 30 wide screen, 28 columns inside the box
+        fn program_view(app: &App) -> (String, usize) {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 30, 20));
+            app.render(buf.area, &mut buf);
+            let rows = rendered_rows(&buf);
+            let text: String = rows[17].chars().skip(1).take(28).collect();
+            let caret = rows[18].chars().position(|c| c == '^').unwrap();
+            (text.trim_end().to_string(), caret)
+        }
+
+        let mut app = App::new();
+        assert_eq!(program_view(&app), (String::new(), 1));
+
+        let prog = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMN";
+        app.load_program(prog.as_bytes());
+        run_command(&mut app, "step 20");
+        assert_eq!(program_scroll(20, 28), 0);
+        assert_eq!(program_view(&app), (prog[..28].to_string(), 21));
+
+        run_command(&mut app, "step 15");
+        assert_eq!(program_scroll(35, 28), 8);
+        assert_eq!(program_view(&app), (prog[8..36].to_string(), 28));
+
+        // at the end the caret sits one past the last instruction
+        run_command(&mut app, "step 5");
+        assert_eq!(app.interp.prog_ptr, 40);
+        assert_eq!(program_view(&app), (prog[13..].to_string(), 28));
+    }
+
+    #[test]
+    fn speed_sets_the_steps_per_frame() {
+        let mut app = App::new();
+        run_command(&mut app, "speed 100");
+        assert_eq!(app.options.steps_per_frame, 100);
+        assert_eq!(app.error_str, "running 100 steps per frame");
+
+        run_command(&mut app, "speed abc");
+        assert_eq!(app.error_str, "speed: expected a step count, got 'abc'");
+        assert!(!app.error_is_info);
+        run_command(&mut app, "speed -5");
+        assert_eq!(app.options.steps_per_frame, 100);
+
+        // 0 pauses without losing the speed
+        app.auto_run = true;
+        run_command(&mut app, "speed 0");
+        assert!(!app.auto_run);
+        assert_eq!(app.options.steps_per_frame, 100);
+    }
+
+    #[test]
+    fn editing_mode_appends_submitted_code_to_the_program() {
+        fn type_code(app: &mut App, code: &str) {
+            for c in code.chars() {
+                app.dispatch_input(KeyCode::Char(c), KeyModifiers::NONE);
+            }
+            app.dispatch_input(KeyCode::Enter, KeyModifiers::NONE);
+        }
+
+        let mut app = App::new();
+        app.normal_key(KeyCode::Char('e'), KeyModifiers::NONE);
+        assert_eq!(app.mode, Mode::Editing);
+        type_code(&mut app, "++[>+<-]");
+        type_code(&mut app, ">.");
+        assert_eq!(app.interp.prog, b"++[>+<-]>.");
+        assert_eq!(app.mode, Mode::Editing);
+        assert!(app.edit_field.get_str().is_empty());
+        let code: Vec<&str> = app
+            .lines
+            .iter()
+            .filter(|l| matches!(l.kind, ReplType::Code))
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_eq!(code, ["++[>+<-]", ">."]);
+
+        // a stray bracket is refused and kept in the field to fix
+        type_code(&mut app, "]");
+        assert_eq!(app.interp.prog, b"++[>+<-]>.");
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.edit_field.get_str(), "]");
+        assert!(!app.error_is_info);
+    }
+
+    #[test]
+    fn help_opens_the_reference_popup() {
+        let mut app = App::new();
+        run_command(&mut app, "help");
+        assert!(app.message_popup.status == PopupStatus::InUse);
+        assert!(app.dialogue == Dialogue::Message);
+        assert_eq!(app.message_popup.title, " Help ");
+        let body = &app.message_popup.body;
+        assert!(KEY_BINDINGS.iter().all(|(_, desc)| body.contains(desc)));
+        assert!(COMMANDS
+            .iter()
+            .all(|(usage, desc)| body.contains(&format!(":{usage}")) && body.contains(desc)));
+
+        // ? opens the same popup
+        app.dispatch_input(KeyCode::Esc, KeyModifiers::NONE);
+        app.update_dialogue();
+        assert!(app.dialogue == Dialogue::None);
+        app.normal_key(KeyCode::Char('?'), KeyModifiers::NONE);
+        assert!(app.message_popup.status == PopupStatus::InUse);
+    }
 }
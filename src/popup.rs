@@ -32,8 +32,11 @@ impl TextEntryPopup {
             KeyCode::Esc => self.cancel(),
             KeyCode::Char(c) => self.text_field.insert(c),
             KeyCode::Backspace => self.text_field.remove(),
+            KeyCode::Delete => self.text_field.delete_forward(),
             KeyCode::Left => self.text_field.move_cursor_left(),
             KeyCode::Right => self.text_field.move_cursor_right(),
+            KeyCode::Home => self.text_field.move_cursor_home(),
+            KeyCode::End => self.text_field.move_cursor_end(),
             _ => input_captured = false,
         }
 
@@ -49,6 +52,14 @@ impl TextEntryPopup {
         }
     }
 
+    // starts with `default` filled in and the cursor after it, reset still empties it
+    pub fn with_default(title: String, max_lines: u16, default: String) -> Self {
+        let mut popup = TextEntryPopup::new(title, max_lines);
+        popup.text_field.set_text(default);
+        popup.text_field.move_cursor_end();
+        popup
+    }
+
     fn confirm(&mut self) {
         self.status = PopupStatus::Confirmed;
     }
@@ -88,30 +99,58 @@ impl Widget for &TextEntryPopup {
             .border_type(BorderType::Rounded)
             .title(self.title.as_str())
             .title_bottom(
-                Line::raw(format!(" [Esc] to Cancel [Enter] to Confirm "))
-                    .alignment(Alignment::Right),
+                Line::raw(" [Esc] to Cancel [Enter] to Confirm ").alignment(Alignment::Right),
             );
 
         let win_area = window.inner(area);
         Clear.render(win_area, buf);
         window.render(area, buf);
 
-        Paragraph::new(self.text_field.get_str())
-            .wrap(Wrap { trim: true })
+        // wrap at exactly the window width so the cursor maths matches what is drawn,
+        // scrolling down once the cursor goes past the last of `max_lines` rows
+        let width = win_area.width.max(1) as usize;
+        let rows = win_area.height.min(self.max_lines).max(1) as usize;
+        let cursor_pos = self.text_field.get_cursor_pos();
+        let first_row = (cursor_pos / width + 1).saturating_sub(rows);
+        // wrapped in the same units the cursor moves in
+        let units = self.text_field.units();
+        let lines: Vec<Line> = units
+            .chunks(width)
+            .skip(first_row)
+            .take(rows)
+            .map(|row| Line::from(row.concat()))
+            .collect();
+        Paragraph::new(lines)
             .style(THEME.popup_selected)
             .render(win_area, buf);
 
-        let cursor_pos = self.text_field.get_cursor_pos() as i32;
-        Span::from("█").style(THEME.popup_selected).render(
-            win_area.offset(Offset {
-                x: cursor_pos % 58,
-                y: cursor_pos / 58,
-            }),
-            buf,
-        );
+        let x = (cursor_pos % width) as i32;
+        let y = (cursor_pos / width - first_row) as i32;
+        if let Some(cursor_area) = clamp_cursor(win_area, x, y) {
+            Span::from("█")
+                .style(THEME.popup_selected)
+                .render(cursor_area, buf);
+        }
     }
 }
 
+// keeps a cursor offset inside `area`, returns None if there is nowhere to draw it
+fn clamp_cursor(area: Rect, x: i32, y: i32) -> Option<Rect> {
+    if area.width == 0 || area.height == 0 {
+        return None;
+    }
+
+    let offset = Offset {
+        x: x.clamp(0, area.width as i32 - 1),
+        y: y.clamp(0, area.height as i32 - 1),
+    };
+    Some(Rect {
+        width: 1,
+        height: 1,
+        ..area.offset(offset)
+    })
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub enum ConfirmationField {
     Yes,
@@ -134,8 +173,13 @@ pub struct ConfirmationPopup {
     pub status: PopupStatus,
 
     selected_field: ConfirmationField,
+    scroll: u16,
 }
 
+// body rows shown before a confirmation popup starts scrolling
+const CONFIRM_BODY_ROWS: usize = 8;
+const CONFIRM_WIDTH: u16 = 45;
+
 impl ConfirmationPopup {
     pub fn handle_input(&mut self, key: KeyCode) -> bool {
         match key {
@@ -166,6 +210,15 @@ impl ConfirmationPopup {
                 self.status = PopupStatus::Confirmed;
                 true
             }
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                true
+            }
+            KeyCode::Down => {
+                let max_scroll = self.wrapped_rows().saturating_sub(self.body_rows()) as u16;
+                self.scroll = (self.scroll + 1).min(max_scroll);
+                true
+            }
             KeyCode::Char('q') => true,
             _ => false,
         }
@@ -177,11 +230,30 @@ impl ConfirmationPopup {
             title: new_title,
             body: new_body,
             status: PopupStatus::Closed,
+            scroll: 0,
         }
     }
 
+    fn body_paragraph(&self) -> Paragraph<'_> {
+        Paragraph::new(self.body.as_str())
+            .style(THEME.popup)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+    }
+
+    // rows the body takes once long lines wrap inside the borders
+    fn wrapped_rows(&self) -> usize {
+        self.body_paragraph().line_count(CONFIRM_WIDTH - 2)
+    }
+
+    // the box grows with the body up to a cap, past that the body scrolls
+    fn body_rows(&self) -> usize {
+        self.wrapped_rows().clamp(1, CONFIRM_BODY_ROWS)
+    }
+
     pub fn show(&mut self) {
         self.selected_field = ConfirmationField::No;
+        self.scroll = 0;
         self.status = PopupStatus::InUse;
     }
 
@@ -199,8 +271,8 @@ impl ConfirmationPopup {
 
 impl Widget for &ConfirmationPopup {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let vertical = Layout::vertical([5]).flex(Flex::Center);
-        let horizontal = Layout::horizontal([45]).flex(Flex::Center);
+        let vertical = Layout::vertical([self.body_rows() as u16 + 4]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([CONFIRM_WIDTH]).flex(Flex::Center);
         let [area] = vertical.areas(area);
         let [area] = horizontal.areas(area);
 
@@ -228,9 +300,8 @@ impl Widget for &ConfirmationPopup {
         ]);
         let [body_area, _gap, button_area] = vertical.areas(win_area);
 
-        Paragraph::new(self.body.as_str())
-            .style(THEME.popup)
-            .alignment(Alignment::Center)
+        self.body_paragraph()
+            .scroll((self.scroll, 0))
             .render(body_area, buf);
 
         Line::from(vec![
@@ -250,3 +321,309 @@ impl Widget for &ConfirmationPopup {
         .render(button_area, buf);
     }
 }
+
+#[derive(Default)]
+pub struct MessagePopup {
+    pub title: String,
+    pub body: String,
+    pub status: PopupStatus,
+
+    scroll: u16,
+}
+
+impl MessagePopup {
+    pub fn handle_input(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Enter => self.status = PopupStatus::Confirmed,
+            KeyCode::Esc => self.status = PopupStatus::Canceled,
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => {
+                let max_scroll = self.body.lines().count().saturating_sub(1) as u16;
+                self.scroll = (self.scroll + 1).min(max_scroll);
+            }
+            KeyCode::Char('q') => {}
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn show(&mut self, title: String, body: String) {
+        self.title = title;
+        self.body = body;
+        self.scroll = 0;
+        self.status = PopupStatus::InUse;
+    }
+
+    pub fn close(&mut self) {
+        self.status = PopupStatus::Closed;
+    }
+}
+
+impl Widget for &MessagePopup {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let body_lines = self.body.lines().count().clamp(1, 20) as u16;
+        let body_width = self.body.lines().map(str::len).max().unwrap_or(0) as u16;
+        let vertical = Layout::vertical([body_lines + 2]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([body_width.clamp(36, 76) + 4]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let window = Block::bordered()
+            .style(THEME.popup)
+            .border_style(THEME.popup)
+            .border_type(BorderType::Rounded)
+            .title(Span::from(&self.title))
+            .title_bottom(Line::raw(" [Enter] to Close ").alignment(Alignment::Right));
+
+        let win_area = window.inner(area);
+        Clear.render(win_area, buf);
+        window.render(area, buf);
+
+        Paragraph::new(self.body.as_str())
+            .style(THEME.popup)
+            .scroll((self.scroll, 0))
+            .render(win_area.inner(Margin::new(1, 0)), buf);
+    }
+}
+
+#[derive(Default)]
+pub struct ListPopup {
+    pub title: String,
+    pub options: Vec<String>,
+    pub selected: usize,
+    pub status: PopupStatus,
+}
+
+impl ListPopup {
+    pub fn handle_input(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Enter if !self.options.is_empty() => self.status = PopupStatus::Confirmed,
+            KeyCode::Esc => self.status = PopupStatus::Canceled,
+            // moving off either end wraps round to the other
+            KeyCode::Up if !self.options.is_empty() => {
+                self.selected = self
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(self.options.len() - 1)
+            }
+            KeyCode::Down if !self.options.is_empty() => {
+                self.selected = (self.selected + 1) % self.options.len()
+            }
+            KeyCode::Enter | KeyCode::Up | KeyCode::Down | KeyCode::Char('q') => {}
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn show(&mut self, title: String, options: Vec<String>) {
+        self.title = title;
+        self.options = options;
+        self.selected = 0;
+        self.status = PopupStatus::InUse;
+    }
+
+    pub fn close(&mut self) {
+        self.status = PopupStatus::Closed;
+    }
+
+    pub fn selection(&self) -> Option<&str> {
+        self.options.get(self.selected).map(String::as_str)
+    }
+}
+
+impl Widget for &ListPopup {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let rows = self.options.len().clamp(1, 20);
+        let width = self.options.iter().map(String::len).max().unwrap_or(0) as u16;
+        let vertical = Layout::vertical([rows as u16 + 2]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([width.clamp(36, 76) + 4]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let window = Block::bordered()
+            .style(THEME.popup)
+            .border_style(THEME.popup)
+            .border_type(BorderType::Rounded)
+            .title(Span::from(&self.title))
+            .title_bottom(
+                Line::raw(" [Esc] to Cancel [Enter] to Select ").alignment(Alignment::Right),
+            );
+
+        let win_area = window.inner(area);
+        Clear.render(win_area, buf);
+        window.render(area, buf);
+
+        // scroll just far enough to keep the selection in view
+        let visible = (win_area.height as usize).max(1);
+        let first = (self.selected + 1).saturating_sub(visible);
+        let lines: Vec<Line> = self
+            .options
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, option)| {
+                let style = if i == self.selected {
+                    THEME.popup_selected
+                } else {
+                    THEME.popup
+                };
+                Line::from(option.as_str()).style(style)
+            })
+            .collect();
+        Paragraph::new(lines)
+            .style(THEME.popup)
+            .render(win_area.inner(Margin::new(1, 0)), buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_long_confirmation_line_wraps_and_scrolls() {
+        let body = "word ".repeat(100);
+        let mut popup = ConfirmationPopup::new(" Confirm ".to_string(), body);
+        popup.show();
+        assert!(popup.wrapped_rows() > CONFIRM_BODY_ROWS);
+        assert_eq!(popup.body_rows(), CONFIRM_BODY_ROWS);
+
+        for _ in 0..100 {
+            popup.handle_input(KeyCode::Down);
+        }
+        assert_eq!(
+            popup.scroll as usize,
+            popup.wrapped_rows() - CONFIRM_BODY_ROWS
+        );
+    }
+
+    #[test]
+    fn a_short_confirmation_body_does_not_scroll() {
+        let mut popup = ConfirmationPopup::new(" Confirm ".to_string(), "sure?".to_string());
+        popup.show();
+        assert_eq!(popup.body_rows(), 1);
+        popup.handle_input(KeyCode::Down);
+        assert_eq!(popup.scroll, 0);
+    }
+
+    fn list(options: &[&str]) -> ListPopup {
+        let mut popup = ListPopup::default();
+        popup.show(
+            " List ".to_string(),
+            options.iter().map(|o| o.to_string()).collect(),
+        );
+        popup
+    }
+
+    #[test]
+    fn list_selection_wraps_at_both_ends() {
+        let mut popup = list(&["a", "b", "c"]);
+        popup.handle_input(KeyCode::Up);
+        assert_eq!(popup.selection(), Some("c"));
+        popup.handle_input(KeyCode::Down);
+        assert_eq!(popup.selection(), Some("a"));
+    }
+
+    #[test]
+    fn list_enter_confirms_and_esc_cancels() {
+        let mut popup = list(&["a", "b"]);
+        popup.handle_input(KeyCode::Down);
+        popup.handle_input(KeyCode::Enter);
+        assert!(popup.status == PopupStatus::Confirmed);
+        assert_eq!(popup.selection(), Some("b"));
+
+        let mut popup = list(&["a", "b"]);
+        popup.handle_input(KeyCode::Esc);
+        assert!(popup.status == PopupStatus::Canceled);
+    }
+
+    #[test]
+    fn an_empty_list_cannot_be_confirmed() {
+        let mut popup = list(&[]);
+        popup.handle_input(KeyCode::Down);
+        popup.handle_input(KeyCode::Enter);
+        assert!(popup.status == PopupStatus::InUse);
+        assert_eq!(popup.selection(), None);
+    }
+
+    #[test]
+    fn the_cursor_is_clamped_into_a_small_area() {
+        let area = Rect::new(2, 1, 10, 3);
+        assert_eq!(clamp_cursor(area, 4, 1), Some(Rect::new(6, 2, 1, 1)));
+        assert_eq!(clamp_cursor(area, 58, 7), Some(Rect::new(11, 3, 1, 1)));
+        assert_eq!(clamp_cursor(area, -3, -1), Some(Rect::new(2, 1, 1, 1)));
+        assert_eq!(clamp_cursor(Rect::new(2, 1, 0, 3), 0, 0), None);
+    }
+
+    #[test]
+    fn a_text_popup_renders_inside_a_10x3_area() {
+        let mut popup = TextEntryPopup::with_default(" Save ".to_string(), 3, "x".repeat(39));
+        popup.show();
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::empty(area);
+        popup.render(area, &mut buf);
+
+        // the border leaves one row 8 wide, 39 chars put the cursor in its last column
+        let cursor = (0..10).find(|&x| buf[(x, 1)].symbol() == "█");
+        assert_eq!(cursor, Some(8));
+    }
+
+    // the popup drawn into a 62x5 area, its text area is 58x3 starting at (2, 1)
+    fn render_text_popup(text: &str) -> Buffer {
+        let mut popup = TextEntryPopup::with_default(" Save ".to_string(), 3, text.to_string());
+        popup.show();
+        let area = Rect::new(0, 0, 62, 5);
+        let mut buf = Buffer::empty(area);
+        popup.render(area, &mut buf);
+        buf
+    }
+
+    fn cursor_cell(buf: &Buffer) -> Option<(u16, u16)> {
+        (0..5)
+            .flat_map(|y| (0..62).map(move |x| (x, y)))
+            .find(|&pos| buf[pos].symbol() == "█")
+    }
+
+    #[test]
+    fn the_cursor_follows_a_wrapped_line() {
+        let text: String = ('a'..='z').cycle().take(70).collect();
+        let buf = render_text_popup(&text);
+        assert_eq!(cursor_cell(&buf), Some((14, 2)));
+        // the second row starts with the 59th char
+        assert_eq!(buf[(2, 2)].symbol(), "g");
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn wrapping_counts_graphemes_like_the_cursor() {
+        let text = "e\u{301}".repeat(61);
+        let buf = render_text_popup(&text);
+        assert_eq!(cursor_cell(&buf), Some((5, 2)));
+        assert_eq!(buf[(2, 2)].symbol(), "e\u{301}");
+        assert_eq!(buf[(4, 2)].symbol(), "e\u{301}");
+    }
+
+    #[test]
+    fn with_default_starts_filled_with_the_cursor_at_the_end() {
+        let mut popup = TextEntryPopup::with_default(" Save ".to_string(), 1, "out.bf".to_string());
+        popup.show();
+        assert_eq!(popup.text_field.get_str(), "out.bf");
+        assert_eq!(popup.text_field.get_cursor_pos(), 6);
+
+        popup.handle_input(KeyCode::Char('x'));
+        assert_eq!(popup.text_field.get_str(), "out.bfx");
+
+        popup.reset();
+        assert_eq!(popup.text_field.get_str(), "");
+    }
+
+    #[test]
+    fn home_and_end_move_the_popup_cursor() {
+        let mut popup = TextEntryPopup::with_default(" Save ".to_string(), 1, "out.bf".to_string());
+        assert!(popup.handle_input(KeyCode::Home));
+        assert_eq!(popup.text_field.get_cursor_pos(), 0);
+        assert!(popup.handle_input(KeyCode::End));
+        assert_eq!(popup.text_field.get_cursor_pos(), 6);
+    }
+}
@@ -0,0 +1,434 @@
+use crate::interpreter::{
+    ArithmeticMode, BFInt, CellWidth, EofBehavior, FrozenMode, InterpState, MAX_TAPE_LEN,
+};
+
+// interpreter state as json for bug reports, read back by state_from_json.
+// only the tape up to the last non zero cell (or the pointer) is written
+pub fn state_to_json(interp: &BFInt) -> String {
+    let touched = interp
+        .mem
+        .iter()
+        .rposition(|&v| v != 0)
+        .map_or(0, |last| last + 1)
+        .max(interp.mem_ptr + 1)
+        .min(interp.mem.len());
+    let state = match interp.state {
+        InterpState::Finished => "finished",
+        InterpState::Waiting => "waiting",
+        InterpState::Running => "running",
+    };
+    let arithmetic = match interp.arithmetic {
+        ArithmeticMode::Wrapping => "wrap",
+        ArithmeticMode::Saturating => "sat",
+    };
+    let eof = match interp.eof_behavior {
+        EofBehavior::Wait => "wait",
+        EofBehavior::Unchanged => "keep",
+        EofBehavior::Zero => "zero",
+        EofBehavior::Max => "max",
+    };
+    let frozen_mode = match interp.frozen_mode {
+        FrozenMode::Ignore => "ignore",
+        FrozenMode::Pause => "pause",
+    };
+    let cell_width = interp.cell_width.bits();
+    let limit = |l: Option<usize>| l.map_or("null".to_string(), |l| l.to_string());
+    let mut frozen: Vec<usize> = interp.frozen.iter().copied().collect();
+    frozen.sort_unstable();
+    let mut breakpoints: Vec<usize> = interp.breakpoints.iter().copied().collect();
+    breakpoints.sort_unstable();
+    let loop_map: Vec<String> = interp
+        .loop_map
+        .iter()
+        .map(|(open, close)| format!("[{open},{close}]"))
+        .collect();
+
+    format!(
+        "{{\"prog\":{},\"prog_ptr\":{},\"mem_ptr\":{},\"state\":\"{state}\",\"step_count\":{},\
+         \"tape_len\":{},\"tape\":{},\"input\":{},\"loop_map\":[{}],\"options\":{{\
+         \"arithmetic\":\"{arithmetic}\",\"cell_width\":{cell_width},\"eof\":\"{eof}\",\"frozen_mode\":\"{frozen_mode}\",\
+         \"output\":{},\"pointer_limit\":{},\"tape_limit\":{},\"frozen\":{},\"breakpoints\":{}}}}}",
+        json_string(&interp.prog),
+        interp.prog_ptr,
+        interp.mem_ptr,
+        interp.step_count,
+        interp.mem.len(),
+        json_array(interp.mem[..touched].iter()),
+        json_array(interp.input.iter()),
+        loop_map.join(","),
+        interp.output_enabled(),
+        limit(interp.pointer_limit),
+        limit(interp.tape_limit),
+        json_array(frozen.iter()),
+        json_array(breakpoints.iter()),
+    )
+}
+
+pub fn state_from_json(text: &str) -> Result<BFInt, String> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    let root = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.text.len() {
+        return Err(format!("trailing data at byte {}", parser.pos));
+    }
+    let options = root.field("options")?;
+
+    let cell_width = match options.field("cell_width")?.number()? {
+        8 => CellWidth::U8,
+        16 => CellWidth::U16,
+        32 => CellWidth::U32,
+        other => return Err(format!("unknown cell width {other}")),
+    };
+    let mut interp = BFInt::with_cell_width(cell_width);
+    // the loop map is rebuilt from the program rather than trusted
+    interp
+        .replace_prog(&root.field("prog")?.bytes()?)
+        .map_err(|e| e.to_string())?;
+    interp.prog_ptr = root.field("prog_ptr")?.number()? as usize;
+    interp.mem_ptr = root.field("mem_ptr")?.number()? as usize;
+    interp.step_count = root.field("step_count")?.number()?;
+    let tape = root.field("tape")?.numbers()?;
+    if let Some(&v) = tape.iter().find(|&&v| v > interp.cell_width.max() as usize) {
+        return Err(format!("{v} does not fit in a cell"));
+    }
+    interp.tape_limit = options.field("tape_limit")?.optional_number()?;
+    let tape_len = (root.field("tape_len")?.number()? as usize).max(tape.len());
+    let max_len = interp.tape_limit.unwrap_or(MAX_TAPE_LEN);
+    if tape_len > max_len {
+        return Err(format!("tape of {tape_len} cells is longer than {max_len}"));
+    }
+    interp.mem = vec![0; tape_len];
+    for (cell, &v) in interp.mem.iter_mut().zip(&tape) {
+        *cell = v as u32;
+    }
+    interp.input = root.field("input")?.bytes()?.into();
+    interp.state = match root.field("state")?.string()? {
+        "finished" => InterpState::Finished,
+        "waiting" => InterpState::Waiting,
+        "running" => InterpState::Running,
+        other => return Err(format!("unknown state '{other}'")),
+    };
+    if interp.prog_ptr > interp.prog.len() || interp.mem_ptr >= interp.mem.len() {
+        return Err("pointer outside the program or tape".to_string());
+    }
+    // step would index past the program
+    if interp.prog_ptr == interp.prog.len() && interp.state != InterpState::Finished {
+        return Err("pc is at the end of the program but the state isn't finished".to_string());
+    }
+
+    interp.arithmetic = match options.field("arithmetic")?.string()? {
+        "wrap" => ArithmeticMode::Wrapping,
+        "sat" => ArithmeticMode::Saturating,
+        other => return Err(format!("unknown arithmetic mode '{other}'")),
+    };
+    interp.eof_behavior = match options.field("eof")?.string()? {
+        "wait" => EofBehavior::Wait,
+        "keep" => EofBehavior::Unchanged,
+        "zero" => EofBehavior::Zero,
+        "max" => EofBehavior::Max,
+        other => return Err(format!("unknown eof behavior '{other}'")),
+    };
+    interp.frozen_mode = match options.field("frozen_mode")?.string()? {
+        "ignore" => FrozenMode::Ignore,
+        "pause" => FrozenMode::Pause,
+        other => return Err(format!("unknown frozen mode '{other}'")),
+    };
+    interp.set_output_enabled(options.field("output")?.boolean()?);
+    interp.pointer_limit = options.field("pointer_limit")?.optional_number()?;
+    interp.frozen = options.field("frozen")?.numbers()?.into_iter().collect();
+    interp.breakpoints = options
+        .field("breakpoints")?
+        .numbers()?
+        .into_iter()
+        .collect();
+    Ok(interp)
+}
+
+fn json_array<'a, T: ToString + 'a>(values: impl Iterator<Item = &'a T>) -> String {
+    let items: Vec<String> = values.map(|v| v.to_string()).collect();
+    format!("[{}]", items.join(","))
+}
+
+// bytes outside printable ascii become \u00XX so any program survives the trip
+fn json_string(bytes: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for &b in bytes {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b' '..=b'~' => out.push(b as char),
+            _ => out.push_str(&format!("\\u{b:04x}")),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// just enough json to read back what state_to_json writes
+enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(Vec<u8>),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("missing field '{name}'")),
+            _ => Err(format!("expected an object holding '{name}'")),
+        }
+    }
+
+    fn number(&self) -> Result<u64, String> {
+        match self {
+            Json::Number(n) => Ok(*n),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+
+    fn optional_number(&self) -> Result<Option<usize>, String> {
+        match self {
+            Json::Null => Ok(None),
+            other => other.number().map(|n| Some(n as usize)),
+        }
+    }
+
+    fn boolean(&self) -> Result<bool, String> {
+        match self {
+            Json::Bool(b) => Ok(*b),
+            _ => Err("expected true or false".to_string()),
+        }
+    }
+
+    fn string(&self) -> Result<&str, String> {
+        match self {
+            Json::String(bytes) => {
+                std::str::from_utf8(bytes).map_err(|_| "expected an ascii string".to_string())
+            }
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    fn numbers(&self) -> Result<Vec<usize>, String> {
+        match self {
+            Json::Array(items) => items
+                .iter()
+                .map(|i| i.number().map(|n| n as usize))
+                .collect(),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+
+    // a string's bytes, or an array of byte values
+    fn bytes(&self) -> Result<Vec<u8>, String> {
+        match self {
+            Json::String(bytes) => Ok(bytes.clone()),
+            Json::Array(items) => items
+                .iter()
+                .map(|i| {
+                    i.number()
+                        .and_then(|n| u8::try_from(n).map_err(|_| format!("{n} is not a byte")))
+                })
+                .collect(),
+            _ => Err("expected a string or an array of bytes".to_string()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.text.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at byte {}", byte as char, self.pos))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if self.text[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(format!("unexpected input at byte {}", self.pos))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.text.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'0'..=b'9') => self.number(),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b't') => self.keyword("true", Json::Bool(true)),
+            Some(b'f') => self.keyword("false", Json::Bool(false)),
+            _ => Err(format!("unexpected input at byte {}", self.pos)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .map(Json::Number)
+            .ok_or_else(|| format!("bad number at byte {start}"))
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(&b) = self.text.get(self.pos) else {
+                return Err("unterminated string".to_string());
+            };
+            self.pos += 1;
+            match b {
+                b'"' => return Ok(bytes),
+                b'\\' => {
+                    let escape = self.text.get(self.pos).copied();
+                    self.pos += 1;
+                    match escape {
+                        Some(b'u') => {
+                            let code = self
+                                .text
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                                .ok_or_else(|| format!("bad \\u escape at byte {}", self.pos))?;
+                            bytes.push(code);
+                            self.pos += 4;
+                        }
+                        Some(c @ (b'"' | b'\\' | b'/')) => bytes.push(c),
+                        Some(b'n') => bytes.push(b'\n'),
+                        Some(b't') => bytes.push(b'\t'),
+                        Some(b'r') => bytes.push(b'\r'),
+                        _ => return Err(format!("bad escape at byte {}", self.pos)),
+                    }
+                }
+                _ => bytes.push(b),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.text.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.text.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(format!("expected ',' or ']' at byte {}", self.pos)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.text.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = String::from_utf8_lossy(&self.string()?).into_owned();
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.text.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(format!("expected ',' or '}}' at byte {}", self.pos)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trips() {
+        let mut interp = BFInt::with_cell_width(CellWidth::U16);
+        interp.extend_prog(b"++[>+<-]>,").unwrap();
+        interp.push_input(b"hi");
+        interp.run_with_limit(5);
+        interp.eof_behavior = EofBehavior::Zero;
+        interp.tape_limit = Some(2000);
+        interp.breakpoints.insert(3);
+
+        let json = state_to_json(&interp);
+        let loaded = state_from_json(&json).unwrap();
+        assert_eq!(state_to_json(&loaded), json);
+        assert_eq!(loaded.prog, interp.prog);
+        assert_eq!(loaded.mem, interp.mem);
+        assert_eq!(loaded.loop_map, interp.loop_map);
+        assert_eq!((loaded.prog_ptr, loaded.mem_ptr), (5, 1));
+        assert_eq!(loaded.state, InterpState::Running);
+    }
+
+    #[test]
+    fn running_state_at_the_end_of_the_program_is_rejected() {
+        let mut interp = BFInt::new();
+        interp.extend_prog(b"+").unwrap();
+        interp.step();
+        let json = state_to_json(&interp).replace("\"finished\"", "\"running\"");
+        assert!(state_from_json(&json).is_err());
+    }
+
+    #[test]
+    fn oversized_tape_is_rejected() {
+        let json = state_to_json(&BFInt::new()).replace(
+            "\"tape_len\":1000",
+            &format!("\"tape_len\":{}", MAX_TAPE_LEN + 1),
+        );
+        assert!(state_from_json(&json).is_err());
+    }
+}
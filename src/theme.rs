@@ -10,6 +10,9 @@ pub struct Theme {
     pub popup_selected: Style,
     pub key_bind: Style,
     pub key_desc: Style,
+    pub program_mark: Style,
+    pub memory_active: Style,
+    pub program_executed: Style,
     pub mode: ModeTheme,
 }
 
@@ -17,6 +20,7 @@ pub struct ModeTheme {
     pub normal: Style,
     pub editing: Style,
     pub command: Style,
+    pub input: Style,
 }
 
 pub const THEME: Theme = Theme {
@@ -28,10 +32,14 @@ pub const THEME: Theme = Theme {
     popup_selected: Style::new().fg(YELLOW).bg(DARK_GRAY),
     key_bind: Style::new().fg(BLACK).bg(DARK_GRAY),
     key_desc: Style::new().fg(DARK_GRAY).bg(BLACK),
+    program_mark: Style::new().fg(YELLOW),
+    memory_active: Style::new().fg(YELLOW).bg(DARK_GRAY),
+    program_executed: Style::new().fg(GRAY),
     mode: ModeTheme {
         normal: Style::new().fg(WHITE).bg(BLUE_3),
         editing: Style::new().fg(WHITE).bg(GREEN_1),
         command: Style::new().fg(WHITE).bg(YELLOW),
+        input: Style::new().fg(WHITE).bg(PINK_2),
     },
 };
 
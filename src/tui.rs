@@ -1,4 +1,5 @@
 use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
@@ -10,11 +11,14 @@ pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 pub fn init() -> io::Result<Tui> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    // pasted text arrives as one event instead of a key press per char
+    stdout().execute(EnableBracketedPaste)?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 pub fn restore() -> io::Result<()> {
     disable_raw_mode()?;
+    stdout().execute(DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
     Ok(())
 }
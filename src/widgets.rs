@@ -1,7 +1,19 @@
+use crate::interpreter::CellWidth;
+use itertools::Itertools;
+
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
+
+// edits remembered for undo, older ones are dropped
+const UNDO_LIMIT: usize = 100;
+
 #[derive(Default)]
 pub struct TextEntry {
     text: String,
     cursor_pos: usize,
+    // (text, cursor_pos) before each edit
+    undo_stack: Vec<(String, usize)>,
+    redo_stack: Vec<(String, usize)>,
 }
 
 impl TextEntry {
@@ -9,6 +21,33 @@ impl TextEntry {
         TextEntry {
             text: String::new(),
             cursor_pos: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    // call before changing the text, a new edit makes anything undone unreachable
+    fn save_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push((self.text.clone(), self.cursor_pos));
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some((text, cursor_pos)) = self.undo_stack.pop() {
+            let current = std::mem::replace(&mut self.text, text);
+            self.redo_stack.push((current, self.cursor_pos));
+            self.cursor_pos = cursor_pos;
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some((text, cursor_pos)) = self.redo_stack.pop() {
+            let current = std::mem::replace(&mut self.text, text);
+            self.undo_stack.push((current, self.cursor_pos));
+            self.cursor_pos = cursor_pos;
         }
     }
 
@@ -16,11 +55,18 @@ impl TextEntry {
         std::mem::take(&mut self.text)
     }
 
+    // an edit like any other, so undo goes back to what was there before
     pub fn set_text(&mut self, new_text: String) {
+        if new_text != self.text {
+            self.save_undo();
+        }
         self.text = new_text;
     }
 
     pub fn clear(&mut self) {
+        if !self.text.is_empty() {
+            self.save_undo();
+        }
         self.text.clear();
         self.move_cursor_home();
     }
@@ -33,10 +79,31 @@ impl TextEntry {
         self.cursor_pos
     }
 
+    // byte offsets of each position the cursor can sit on
+    #[cfg(not(feature = "graphemes"))]
+    fn boundaries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.text.char_indices().map(|(i, _)| i)
+    }
+
+    #[cfg(feature = "graphemes")]
+    fn boundaries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.text.grapheme_indices(true).map(|(i, _)| i)
+    }
+
+    // the text split at every position the cursor can sit on, so each piece is one
+    // step of the cursor
+    pub fn units(&self) -> Vec<&str> {
+        let bounds: Vec<usize> = self.boundaries().chain([self.text.len()]).collect();
+        bounds.windows(2).map(|w| &self.text[w[0]..w[1]]).collect()
+    }
+
+    // counts chars, or grapheme clusters with the graphemes feature
+    fn char_count(&self) -> usize {
+        self.boundaries().count()
+    }
+
     fn byte_index(&self) -> usize {
-        self.text
-            .char_indices()
-            .map(|(i, _)| i)
+        self.boundaries()
             .nth(self.cursor_pos)
             .unwrap_or(self.text.len())
     }
@@ -52,30 +119,379 @@ impl TextEntry {
     }
 
     pub fn move_cursor_end(&mut self) {
-        self.cursor_pos = self.text.len();
+        self.cursor_pos = self.char_count();
     }
 
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_pos < self.text.len() {
+        if self.cursor_pos < self.char_count() {
             self.cursor_pos += 1;
         }
     }
 
+    // whether each cursor position sits on whitespace
+    fn whitespace_map(&self) -> Vec<bool> {
+        self.boundaries()
+            .map(|i| self.text[i..].starts_with(char::is_whitespace))
+            .collect()
+    }
+
+    // back over any whitespace, then to the start of the word before it
+    pub fn move_cursor_word_left(&mut self) {
+        let spaces = self.whitespace_map();
+        let mut pos = self.cursor_pos.min(spaces.len());
+        while pos > 0 && spaces[pos - 1] {
+            pos -= 1;
+        }
+        while pos > 0 && !spaces[pos - 1] {
+            pos -= 1;
+        }
+        self.cursor_pos = pos;
+    }
+
+    // past the rest of the current word and the whitespace after it
+    pub fn move_cursor_word_right(&mut self) {
+        let spaces = self.whitespace_map();
+        let mut pos = self.cursor_pos.min(spaces.len());
+        while pos < spaces.len() && !spaces[pos] {
+            pos += 1;
+        }
+        while pos < spaces.len() && spaces[pos] {
+            pos += 1;
+        }
+        self.cursor_pos = pos;
+    }
+
     pub fn insert(&mut self, c: char) {
-        self.text.insert(self.byte_index(), c);
-        self.move_cursor_right();
+        self.save_undo();
+        let at = self.byte_index();
+        self.text.insert(at, c);
+        // a combining char can join the cluster before it, so find where it landed
+        let end = at + c.len_utf8();
+        self.cursor_pos = self.boundaries().take_while(|&i| i < end).count();
+    }
+
+    // inserts at the cursor and moves it past the inserted text
+    pub fn insert_str(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        self.save_undo();
+        let at = self.byte_index();
+        self.text.insert_str(at, s);
+        let end = at + s.len();
+        self.cursor_pos = self.boundaries().take_while(|&i| i < end).count();
     }
 
     pub fn remove(&mut self) {
-        if self.text.len() == 0 {
+        if self.text.is_empty() {
             return;
         }
+        // cursor_pos counts chars, keep it from pointing past the last one
+        self.cursor_pos = self.cursor_pos.min(self.char_count());
         // stops backspace from acting like del when at the beginning of the string
         if self.cursor_pos == 0 {
             return;
         }
+        self.save_undo();
 
+        let end = self.byte_index();
         self.move_cursor_left();
-        self.text.remove(self.byte_index());
+        self.text.replace_range(self.byte_index()..end, "");
+    }
+
+    // removes the char under the cursor, like the delete key
+    pub fn delete_forward(&mut self) {
+        let start = self.byte_index();
+        if start == self.text.len() {
+            return;
+        }
+        self.save_undo();
+        let end = self
+            .boundaries()
+            .find(|&i| i > start)
+            .unwrap_or(self.text.len());
+        self.text.replace_range(start..end, "");
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MemoryView {
+    #[default]
+    Decimal,
+    Hex,
+    Binary,
+    Glyph, // decimal value followed by its ascii char
+    Ascii, // just the ascii char
+}
+
+impl MemoryView {
+    pub fn cycle_next(&mut self) {
+        *self = match self {
+            MemoryView::Decimal => MemoryView::Hex,
+            MemoryView::Hex => MemoryView::Binary,
+            MemoryView::Binary => MemoryView::Glyph,
+            MemoryView::Glyph => MemoryView::Ascii,
+            MemoryView::Ascii => MemoryView::Decimal,
+        }
+    }
+}
+
+// fixed width so cells line up in columns, wide enough for the largest value of `bits`.
+// Glyph cells vary in width and are padded to cell_width where they are drawn
+pub fn format_cell(value: u32, view: MemoryView, bits: CellWidth) -> String {
+    let digits = bits.max().to_string().len();
+    match view {
+        MemoryView::Decimal => format!("{value:>digits$}"),
+        MemoryView::Hex => format!("{value:0width$X}", width = bits.bits() as usize / 4),
+        MemoryView::Binary => format!("{value:0width$b}", width = bits.bits() as usize),
+        // control and non ascii bytes get a placeholder so the width stays fixed
+        MemoryView::Glyph => match u8::try_from(value) {
+            Ok(b) if b.is_ascii_graphic() || b == b' ' => format!("{value}'{}'", b as char),
+            _ => format!("{value}'.'"),
+        },
+        MemoryView::Ascii => match u8::try_from(value) {
+            Ok(b) if b.is_ascii_graphic() || b == b' ' => (b as char).to_string(),
+            _ => ".".to_string(),
+        },
+    }
+}
+
+pub fn cell_width(view: MemoryView, bits: CellWidth) -> usize {
+    format_cell(bits.max(), view, bits).len()
+}
+
+// how many cells fit on one row of the memory panel, cells are separated by a space
+pub fn cells_per_row(width: u16, view: MemoryView, bits: CellWidth) -> usize {
+    ((width as usize + 1) / (cell_width(view, bits) + 1)).max(1)
+}
+
+// first memory row to draw: the pointer's row at the bottom, moved by whole pages
+// when scrolled by hand, without leaving the tape
+pub fn memory_first_row(
+    ptr_row: usize,
+    visible_rows: usize,
+    total_rows: usize,
+    pages: isize,
+) -> usize {
+    let follow = (ptr_row + 1).saturating_sub(visible_rows) as isize;
+    let last = total_rows.saturating_sub(visible_rows) as isize;
+    (follow + pages * visible_rows as isize).clamp(0, last.max(follow)) as usize
+}
+
+// column of the pointer marker under `mem_ptr` for a row starting at `row_start`
+pub fn marker_column(row_start: usize, mem_ptr: usize, view: MemoryView, bits: CellWidth) -> usize {
+    let width = cell_width(view, bits);
+    (mem_ptr - row_start) * (width + 1) + width / 2
+}
+
+// columns a program byte takes up when drawn at `column`, tabs run to the next stop
+pub fn display_width(byte: u8, column: usize, tab_width: usize) -> usize {
+    if byte == b'\t' && tab_width > 0 {
+        tab_width - column % tab_width
+    } else {
+        1
+    }
+}
+
+// screen column of `offset` once everything before it has been drawn
+pub fn display_column(prog: &[u8], offset: usize, tab_width: usize) -> usize {
+    prog[..offset.min(prog.len())]
+        .iter()
+        .fold(0, |column, &b| column + display_width(b, column, tab_width))
+}
+
+// columns the program view is scrolled right by so `caret` fits in `width` columns,
+// the caret can sit one past the last instruction so that column counts too
+pub fn program_scroll(caret: usize, width: usize) -> usize {
+    (caret + 1).saturating_sub(width.max(1))
+}
+
+pub const DUMP_ROW_BYTES: usize = 8;
+
+// `offset  XX XX ..  ascii` rows, numbering the first byte as `first_offset`
+pub fn hex_dump(bytes: &[u8], first_offset: u64) -> Vec<String> {
+    bytes
+        .chunks(DUMP_ROW_BYTES)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex = chunk.iter().map(|b| format!("{b:02X}")).join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let offset = first_offset + (row * DUMP_ROW_BYTES) as u64;
+            format!(
+                "{offset:>6}  {hex:<width$}  {ascii}",
+                width = DUMP_ROW_BYTES * 3 - 1
+            )
+        })
+        .collect()
+}
+
+// what a terminal would show for `line`: each `\r` returns to column 0 and
+// following chars overwrite what is already there
+pub fn apply_carriage_returns(line: &str) -> String {
+    let mut shown: Vec<char> = Vec::new();
+    for (i, segment) in line.split('\r').enumerate() {
+        let start = if i == 0 { shown.len() } else { 0 };
+        for (column, c) in (start..).zip(segment.chars()) {
+            match shown.get_mut(column) {
+                Some(slot) => *slot = c,
+                None => shown.push(c),
+            }
+        }
+    }
+    shown.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_cells_show_the_value_and_a_safe_char() {
+        assert_eq!(format_cell(65, MemoryView::Glyph, CellWidth::U8), "65'A'");
+        assert_eq!(format_cell(7, MemoryView::Glyph, CellWidth::U8), "7'.'");
+        assert_eq!(cell_width(MemoryView::Glyph, CellWidth::U8), "255'.'".len());
+    }
+
+    fn entry(text: &str, cursor_pos: usize) -> TextEntry {
+        let mut entry = TextEntry::new();
+        entry.set_text(text.to_string());
+        entry.cursor_pos = cursor_pos;
+        entry
+    }
+
+    #[test]
+    fn insert_str_into_the_middle_moves_the_cursor_past_it() {
+        let mut field = entry("a.bf", 1);
+        field.insert_str("éx");
+        assert_eq!(field.get_str(), "aéx.bf");
+        assert_eq!(field.get_cursor_pos(), 3);
+    }
+
+    #[test]
+    fn insert_str_into_an_empty_field() {
+        let mut field = TextEntry::new();
+        field.insert_str("+[-]");
+        assert_eq!(field.get_str(), "+[-]");
+        assert_eq!(field.get_cursor_pos(), 4);
+
+        field.undo();
+        assert_eq!(field.get_str(), "");
+    }
+
+    #[test]
+    fn end_moves_past_the_last_multibyte_char() {
+        let mut field = TextEntry::new();
+        field.insert('a');
+        field.insert('é');
+        field.move_cursor_home();
+        field.move_cursor_end();
+        assert_eq!(field.get_cursor_pos(), 2);
+        field.insert('b');
+        assert_eq!(field.get_str(), "aéb");
+    }
+
+    #[test]
+    fn delete_forward_removes_the_char_under_the_cursor() {
+        let mut field = entry("abc", 1);
+        field.delete_forward();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("ac", 1));
+
+        let mut field = entry("ébc", 0);
+        field.delete_forward();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("bc", 0));
+
+        let mut field = entry("abc", 3);
+        field.delete_forward();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("abc", 3));
+    }
+
+    #[test]
+    fn word_motion_jumps_over_words_and_spaces() {
+        let mut field = entry("load  my file", 13);
+        let mut lefts = Vec::new();
+        for _ in 0..4 {
+            field.move_cursor_word_left();
+            lefts.push(field.get_cursor_pos());
+        }
+        assert_eq!(lefts, [9, 6, 0, 0]);
+
+        let mut rights = Vec::new();
+        for _ in 0..4 {
+            field.move_cursor_word_right();
+            rights.push(field.get_cursor_pos());
+        }
+        assert_eq!(rights, [6, 9, 13, 13]);
+    }
+
+    #[test]
+    fn undo_and_redo_step_through_edits() {
+        let mut field = TextEntry::new();
+        field.insert('a');
+        field.insert('b');
+        field.undo();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("a", 1));
+        field.undo();
+        field.undo();
+        assert_eq!(field.get_str(), "");
+        field.redo();
+        field.redo();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("ab", 2));
+
+        // a new edit after undo drops what could have been redone
+        field.undo();
+        field.insert('c');
+        field.redo();
+        assert_eq!(field.get_str(), "ac");
+    }
+
+    #[test]
+    fn set_text_can_be_undone() {
+        let mut field = TextEntry::new();
+        field.insert('x');
+        field.set_text("step 5".to_string());
+        field.undo();
+        assert_eq!(field.get_str(), "x");
+        field.redo();
+        assert_eq!(field.get_str(), "step 5");
+    }
+
+    #[test]
+    fn backspace_removes_whole_multibyte_chars() {
+        let mut field = entry("aéb", 3);
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            field.remove();
+            seen.push((field.get_str().to_string(), field.get_cursor_pos()));
+        }
+        let expected = [("aé", 2), ("a", 1), ("", 0), ("", 0)];
+        assert_eq!(seen, expected.map(|(text, pos)| (text.to_string(), pos)));
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn the_cursor_steps_over_a_whole_emoji_cluster() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let mut field = entry(&format!("a{family}b"), 0);
+        field.move_cursor_end();
+        assert_eq!(field.get_cursor_pos(), 3);
+
+        field.move_cursor_left();
+        field.remove();
+        assert_eq!((field.get_str(), field.get_cursor_pos()), ("ab", 1));
+
+        field.insert_str(family);
+        field.move_cursor_home();
+        field.move_cursor_right();
+        field.delete_forward();
+        assert_eq!(field.get_str(), "ab");
     }
 }